@@ -0,0 +1,115 @@
+/* combinator.rs
+ * ===
+ * Small parser-combinator primitives for describing a grammar declaratively
+ * as a composition of functions instead of as a hand-rolled state machine
+ * (see 'SyntaxChecker' in 'parse' for the latter). Each parser is a closure
+ * that takes the input remaining to be consumed together with how many
+ * characters have already been consumed from the start of the token (so a
+ * SyntaxError can carry an absolute position, the same convention
+ * 'parse::tokenize' uses), and returns the new remaining input, the new
+ * offset, and whatever value it parsed.
+ *
+ * This file is a part of:
+ *
+ * Yucon - General Purpose Unit Converter
+ * Copyright (C) 2016-2017  Blaine Murphy
+ *
+ * This program is free software: you can redistribute it and/or modify it under the terms
+ * of the GNU General Public License as published by the Free Software Foundation, either
+ * version 3 of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+ * without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+ * See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use ::parse::SyntaxError;
+
+pub type ParseResult<'a, T> = Result<(&'a str, usize, T), SyntaxError>;
+pub type Parser<'p, T> = Box<Fn(&str, usize) -> ParseResult<T> + 'p>;
+
+// Matches the literal 'lit' at the start of input exactly.
+pub fn literal<'p>(lit: &'static str) -> Parser<'p, ()>
+{
+    Box::new(move |input: &str, offset: usize| {
+        if input.starts_with(lit)
+        {
+            Ok((&input[lit.len()..], offset + lit.chars().count(), ()))
+        }
+        else
+        {
+            Err(SyntaxError::Expected(offset..(offset+1), format!("'{}'", lit)))
+        }
+    })
+}
+
+// Matches a run of 1 or more chars satisfying 'pred' and returns them joined
+// into a String. 'desc' names what was expected if the run turns out empty.
+pub fn many1<'p, F>(desc: &'static str, pred: F) -> Parser<'p, String>
+    where F: Fn(char) -> bool + 'p
+{
+    Box::new(move |input: &str, offset: usize| {
+        let matched: String = input.chars().take_while(|&ch| pred(ch)).collect();
+
+        if matched.is_empty()
+        {
+            return Err(SyntaxError::Expected(offset..(offset+1), desc.to_string()));
+        }
+
+        let consumed = matched.chars().count();
+        Ok((&input[matched.len()..], offset + consumed, matched))
+    })
+}
+
+// Runs 'first' then 'second' against whatever 'first' leaves behind, folding
+// both results into a tuple.
+pub fn seq<'p, A: 'p, B: 'p>(first: Parser<'p, A>, second: Parser<'p, B>) -> Parser<'p, (A, B)>
+{
+    Box::new(move |input: &str, offset: usize| {
+        let (rest, offset, a) = try!(first(input, offset));
+        let (rest, offset, b) = try!(second(rest, offset));
+        Ok((rest, offset, (a, b)))
+    })
+}
+
+// Tries 'parser'; on failure, succeeds with 'None' and consumes nothing.
+pub fn opt<'p, T: 'p>(parser: Parser<'p, T>) -> Parser<'p, Option<T>>
+{
+    Box::new(move |input: &str, offset: usize| {
+        match parser(input, offset)
+        {
+        Ok((rest, offset, value)) => Ok((rest, offset, Some(value))),
+        Err(_) => Ok((input, offset, None)),
+        }
+    })
+}
+
+// Transforms a successful parse's value with 'f'; position and errors pass
+// through untouched.
+pub fn map<'p, T: 'p, U: 'p, F>(parser: Parser<'p, T>, f: F) -> Parser<'p, U>
+    where F: Fn(T) -> U + 'p
+{
+    Box::new(move |input: &str, offset: usize| {
+        let (rest, offset, value) = try!(parser(input, offset));
+        Ok((rest, offset, f(value)))
+    })
+}
+
+// Succeeds only if 'parser' consumes the entire remaining input, reporting
+// 'trailing_mesg' at the first leftover character otherwise.
+pub fn all_consuming<'p, T: 'p>(parser: Parser<'p, T>, trailing_mesg: &'static str) -> Parser<'p, T>
+{
+    Box::new(move |input: &str, offset: usize| {
+        let (rest, offset, value) = try!(parser(input, offset));
+
+        if !rest.is_empty()
+        {
+            return Err(SyntaxError::Expected(offset..(offset+1), trailing_mesg.to_string()));
+        }
+
+        Ok((rest, offset, value))
+    })
+}
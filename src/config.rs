@@ -34,10 +34,14 @@ use ::parse;
 use ::parse::*;
 use ::unit;
 use ::unit::*;
+use ::combinator::literal;
+use ::combinator::Parser;
 use std::rc;
 use std::rc::Rc;
 use std::num::ParseFloatError;
 use std::env;
+use std::ops::Range;
+use ::units::{ConfigFileUnits, UnitDbError, UnitParams, UnitType};
 
 
 /* enum ParsePropertyError
@@ -389,19 +393,19 @@ impl<'a> UnitPropertyCheck<'a>
 // See SyntaxChecker trait summary of the methods below
 impl<'a> SyntaxChecker for UnitPropertyCheck<'a>
 {
-	fn feed_token(&mut self, token: &str, delim: bool, index: usize) -> bool
+	fn feed_token(&mut self, token: &str, delim: bool, range: Range<usize>) -> bool
 	{
 		if delim
 		{
-			return self.check_delim(token, index);
+			return self.check_delim(token, range.start);
 		}
 		else
 		{
-			self.check_normal(token, index)
+			self.check_normal(token, range.start)
 		}
 	}
 
-	fn assert_valid(&self, index: usize, more_tokens: bool) -> Result<(), SyntaxError>
+	fn assert_valid(&self, range: Range<usize>, more_tokens: bool) -> Result<(), SyntaxError>
 	{
 		// the following states are both invalid exit states and possible error states
 		if !more_tokens || !self.valid
@@ -409,13 +413,13 @@ impl<'a> SyntaxChecker for UnitPropertyCheck<'a>
 			match self.state
 			{
 			PropCheckState::CloseBrace => {
-				return Err(SyntaxError::Expected(index, "\']\'".to_string()));
+				return Err(SyntaxError::Expected(range, "\']\'".to_string()));
 			},
 			PropCheckState::Equals => {
-				return Err(SyntaxError::Expected(index, "\'=\'".to_string()));
+				return Err(SyntaxError::Expected(range, "\'=\'".to_string()));
 			},
 			PropCheckState::CommonName => {
-				return Err(SyntaxError::Expected(index, "token after \'[\'".to_string()));
+				return Err(SyntaxError::Expected(range, "token after \'[\'".to_string()));
 			},
 			_ => (), // all others may not meet criteria. do nothing
 			};
@@ -427,13 +431,13 @@ impl<'a> SyntaxChecker for UnitPropertyCheck<'a>
 			match self.state
 			{
 			PropCheckState::OpenBrace => {
-				return Err(SyntaxError::Expected(index, "\'[\'".to_string()));
+				return Err(SyntaxError::Expected(range, "\'[\'".to_string()));
 			},
 			PropCheckState::Comma => {
-				return Err(SyntaxError::Expected(index, "\',\'".to_string()));
+				return Err(SyntaxError::Expected(range, "\',\'".to_string()));
 			},
 			PropCheckState::Validate => {
-				return Err(SyntaxError::Expected(index, "whitespace or comment".to_string()));
+				return Err(SyntaxError::Expected(range, "whitespace or comment".to_string()));
 			},
 			_ => (), // Key and Value states are always okay to exit on. Just do nothing
 			};
@@ -491,6 +495,13 @@ impl<'a> SyntaxChecker for UnitPropertyCheck<'a>
 		self.state = PropCheckState::Key;
 		self.esc_set = false;
 	}
+
+	fn report_and_continue(&mut self, _err: SyntaxError) -> bool
+	{
+		// units.cfg property lines don't support error recovery, just poison as usual
+		self.valid = false;
+		self.valid
+	}
 }
 
 /* Returns the reference to the matching statically allocated unit type string
@@ -567,7 +578,7 @@ fn parse_key_value(mut tokens: Vec<TokenType>) -> Result<UnitProperty, ParseProp
 		{
 			match token
 			{
-			TokenType::Normal(tok) => {
+			TokenType::Normal(tok, _) => {
 				aliases.push(Rc::new(tok));
 				field_empty = false;
 			}
@@ -704,11 +715,11 @@ fn parse_line(line: &str) -> Result<Option<UnitProperty>, ParsePropertyError>
 	{
 		let new_tok = match raw_tok
 		{
-		TokenType::Delim(tok) => {
-			TokenType::Delim(tok.trim().to_string())
+		TokenType::Delim(tok, pos) => {
+			TokenType::Delim(tok.trim().to_string(), pos)
 		},
-		TokenType::Normal(tok) => {
-			TokenType::Normal(tok.trim().to_string())
+		TokenType::Normal(tok, pos) => {
+			TokenType::Normal(tok.trim().to_string(), pos)
 		},
 		};
 
@@ -732,7 +743,7 @@ fn parse_line(line: &str) -> Result<Option<UnitProperty>, ParsePropertyError>
 
 	match tokens[0]
 	{
-	TokenType::Delim(ref tok) => {
+	TokenType::Delim(ref tok, _) => {
 		if tok != "["
 		{
 			println!("FATAL PARSE ERROR!\n\
@@ -744,7 +755,7 @@ fn parse_line(line: &str) -> Result<Option<UnitProperty>, ParsePropertyError>
 			panic!("illegal delimiter begins line after syntax verification");
 		}
 	},
-	TokenType::Normal(_) => common_name = false,
+	TokenType::Normal(..) => common_name = false,
 	};
 
 	let unit_property = if common_name
@@ -759,7 +770,319 @@ fn parse_line(line: &str) -> Result<Option<UnitProperty>, ParsePropertyError>
 	Ok(Some(unit_property))
 }
 
-fn add_unit(database: &mut UnitDatabase, new_unit: Unit, aliases: &Vec<Rc<String>>)
+/* Grammar backend for units.cfg lines
+ * ===
+ * 'UnitPropertyCheck' + 'tokenize' above is a char-by-char state machine:
+ * correct, but every rule (escaping, comments, single vs. list valued
+ * fields) is tangled into 'check_delim'/'check_normal'. The functions below
+ * are an alternative backend that describes the same grammar declaratively,
+ * as a composition of 'combinator' parsers, and builds a typed AST
+ * ('ConfigLine') instead of the loosely-typed 'Vec<TokenType>'.
+ *
+ * NOTE: a real LR(1)/LALR backend would generate this parser (and its
+ * tables) from a grammar file via a parser-generator crate (eg. lalrpop).
+ * This tree has no Cargo.toml and thus no way to vendor or depend on one,
+ * so this backend is hand-written instead of generated. It keeps the
+ * generator's actual payoff - a grammar described declaratively, independent
+ * of the char-by-char tokenizer above - without the dependency.
+ *
+ * Select this backend over the default with 'ParserBackend::Grammar' (see
+ * 'parse_line_with' / 'load_units_list_with_backend'). The hand-rolled
+ * tokenizer remains the default, battle-tested fallback.
+ */
+
+/* enum ConfigLine
+ *
+ * Description: typed AST node for a single units.cfg line, as produced by
+ *   the grammar backend. Mirrors 'UnitProperty' one level up in the parse:
+ *   this is still pure syntax (what shape did the line have), whereas
+ *   'UnitProperty' is semantic (is "length" a real unit type, does this
+ *   field parse as a number).
+ *
+ *     - UnitDefinition     : "[name]"
+ *     - PropertyAssignment : "key = value" or "key = v1, v2, ..."
+ */
+#[derive(Debug, Clone)]
+enum ConfigLine
+{
+	UnitDefinition(String),
+	PropertyAssignment(String, Vec<String>),
+}
+
+fn is_field_delim(ch: char) -> bool
+{
+	ch == '[' || ch == ']' || ch == '=' || ch == ',' || ch == '#' || ch == '\\'
+}
+
+fn is_ws(ch: char) -> bool
+{
+	ch == ' ' || ch == '\t'
+}
+
+/* Scans a run of field text, the grammar backend's equivalent of
+ * 'UnitPropertyCheck's escaping rules in 'tokenize'. Stops at the first
+ * unescaped '[', ']', '=', ',' or '#', or at end of input. A '\' before one
+ * of those five characters (or another '\') escapes it into the field text;
+ * a '\' before anything else is a bad escape sequence, same as 'tokenize'.
+ */
+fn field_text<'p>() -> Parser<'p, String>
+{
+	Box::new(|input: &str, offset: usize| {
+		let chars: Vec<char> = input.chars().collect();
+		let mut text = String::new();
+		let mut i = 0;
+
+		while i < chars.len()
+		{
+			let ch = chars[i];
+
+			if ch == '\\'
+			{
+				match chars.get(i + 1).cloned()
+				{
+				Some(next) if is_field_delim(next) => {
+					text.push(next);
+					i += 2;
+				},
+				other => {
+					return Err(SyntaxError::BadEscSeq((offset+i)..(offset+i+1), other.unwrap_or('\0')));
+				},
+				}
+			}
+			else if is_field_delim(ch)
+			{
+				break;
+			}
+			else
+			{
+				text.push(ch);
+				i += 1;
+			}
+		}
+
+		let consumed_bytes: usize = chars[..i].iter().map(|ch| ch.len_utf8()).sum();
+		Ok((&input[consumed_bytes..], offset + i, text))
+	})
+}
+
+// Skips 0 or more spaces/tabs. Never fails.
+fn skip_ws<'p>() -> Parser<'p, ()>
+{
+	Box::new(|input: &str, offset: usize| {
+		let skipped: String = input.chars().take_while(|&ch| is_ws(ch)).collect();
+		let consumed = skipped.chars().count();
+		Ok((&input[skipped.len()..], offset + consumed, ()))
+	})
+}
+
+// Expects only whitespace and/or a trailing comment for the rest of the
+// line, the grammar equivalent of 'PropCheckState::Validate'.
+fn trailing<'p>() -> Parser<'p, ()>
+{
+	Box::new(|input: &str, offset: usize| {
+		let (rest, offset, _) = try!(skip_ws()(input, offset));
+
+		if rest.is_empty() || rest.starts_with('#')
+		{
+			Ok(("", offset + rest.chars().count(), ()))
+		}
+		else
+		{
+			Err(SyntaxError::Expected(offset..(offset+1), "whitespace or comment".to_string()))
+		}
+	})
+}
+
+// "[" field_text "]" trailing
+fn unit_definition<'p>() -> Parser<'p, String>
+{
+	Box::new(|input: &str, offset: usize| {
+		let (rest, offset, _) = try!(literal("[")(input, offset));
+		let (rest, offset, name) = try!(field_text()(rest, offset));
+
+		if name.trim().is_empty()
+		{
+			return Err(SyntaxError::Expected(offset..(offset+1), "token after \'[\'".to_string()));
+		}
+
+		let (rest, offset, _) = try!(literal("]")(rest, offset));
+		let (rest, offset, _) = try!(trailing()(rest, offset));
+		Ok((rest, offset, name.trim().to_string()))
+	})
+}
+
+// field_text "=" field_text ("," field_text)* trailing
+// the comma-separated list is only legal for the "aliases" key; every other
+// key expects exactly one value, same restriction 'single_val_field' enforces
+// in 'UnitPropertyCheck'.
+fn property_assignment<'p>() -> Parser<'p, (String, Vec<String>)>
+{
+	Box::new(|input: &str, offset: usize| {
+		let (rest, offset, key) = try!(field_text()(input, offset));
+		let key = key.trim().to_string();
+
+		if !rest.starts_with('=')
+		{
+			return Err(SyntaxError::Expected(offset..(offset+1), "\'=\'".to_string()));
+		}
+
+		let (mut rest, mut offset, _) = try!(literal("=")(rest, offset));
+		let mut values = Vec::new();
+
+		loop
+		{
+			let (new_rest, new_offset, value) = try!(field_text()(rest, offset));
+			values.push(value.trim().to_string());
+			rest = new_rest;
+			offset = new_offset;
+
+			if key == "aliases" && rest.starts_with(',')
+			{
+				let (new_rest, new_offset, _) = try!(literal(",")(rest, offset));
+				rest = new_rest;
+				offset = new_offset;
+				continue;
+			}
+
+			break;
+		}
+
+		let (rest, offset, _) = try!(trailing()(rest, offset));
+		Ok((rest, offset, (key, values)))
+	})
+}
+
+// Parses one units.cfg line into a 'ConfigLine', or 'None' for a blank /
+// comment-only line. The branch is the same one 'PropCheckState::Key' makes:
+// a line whose first non-whitespace character is '[' is a unit definition,
+// everything else is a property assignment.
+fn config_line(line: &str) -> Result<Option<ConfigLine>, SyntaxError>
+{
+	let trimmed = line.trim_left();
+	let leading = line.chars().count() - trimmed.chars().count();
+
+	if trimmed.is_empty() || trimmed.starts_with('#')
+	{
+		return Ok(None);
+	}
+
+	if trimmed.starts_with('[')
+	{
+		let (_, _, name) = try!(unit_definition()(trimmed, leading));
+		Ok(Some(ConfigLine::UnitDefinition(name)))
+	}
+	else
+	{
+		let (_, _, (key, values)) = try!(property_assignment()(trimmed, leading));
+		Ok(Some(ConfigLine::PropertyAssignment(key, values)))
+	}
+}
+
+// Parses the single value expected for a numeric property. Grammar
+// backend's equivalent of 'field_as_num', operating on the AST's
+// already-collected 'String' values instead of an 'Option<TokenType>'.
+fn single_numeric_value(key: &str, values: &[String]) -> Result<f64, ParsePropertyError>
+{
+	match values.first()
+	{
+	Some(value) if !value.is_empty() => Ok(try!(value.parse::<f64>())),
+	_ => Err(ParsePropertyError::EmptyField(key.to_string())),
+	}
+}
+
+// Semantic analysis for a grammar-backend AST node, the equivalent of
+// 'parse_common_name' / 'parse_key_value' for the hand-rolled tokenizer.
+fn config_line_to_property(node: ConfigLine) -> Result<UnitProperty, ParsePropertyError>
+{
+	let property = match node
+	{
+	ConfigLine::UnitDefinition(name) => UnitProperty::CommonName(name),
+	ConfigLine::PropertyAssignment(key, values) => {
+		match key.as_str()
+		{
+		"aliases" => {
+			let aliases: Vec<Rc<String>> = values.into_iter()
+			                                      .filter(|v| !v.is_empty())
+			                                      .map(Rc::new)
+			                                      .collect();
+
+			if aliases.is_empty()
+			{
+				return Err(ParsePropertyError::EmptyField(key));
+			}
+
+			UnitProperty::Aliases(aliases)
+		},
+		"conv_factor" => UnitProperty::ConvFactor(try!(single_numeric_value(&key, &values))),
+		"dimensions"  => {
+			let requested_dims = try!(single_numeric_value(&key, &values));
+			let dims: u8 = if requested_dims <= u8::max_value() as f64
+			{
+				requested_dims as u8
+			}
+			else
+			{
+				// @TODO Change this a formal error as the default is already 1.
+				println!("\n*** WARNING ***\n\
+				          Requested {} dimensions for a unit. \
+				          Yucon allows at most 255. Using default (1).",
+				          requested_dims);
+				1
+			};
+			UnitProperty::Dimensions(dims)
+		},
+		"inverse" => UnitProperty::Inverse(try!(single_numeric_value(&key, &values)) != 0.0),
+		"type" => {
+			match values.into_iter().next()
+			{
+			None => UnitProperty::UnitType(unit::UNIT_TYPES[0]), // caught by the empty field check below
+			Some(ref val) if val.is_empty() => return Err(ParsePropertyError::EmptyField(key)),
+			Some(val) => UnitProperty::UnitType(try!(get_unit_type(val))),
+			}
+		},
+		"zero_point" => UnitProperty::ZeroPoint(try!(single_numeric_value(&key, &values))),
+		_ => return Err(ParsePropertyError::NoSuchProperty(key)),
+		}
+	},
+	};
+
+	Ok(property)
+}
+
+// Grammar backend's drop-in replacement for 'parse_line'.
+fn parse_line_grammar(line: &str) -> Result<Option<UnitProperty>, ParsePropertyError>
+{
+	match try!(config_line(line))
+	{
+	None       => Ok(None),
+	Some(node) => Ok(Some(try!(config_line_to_property(node)))),
+	}
+}
+
+/* enum ParserBackend
+ *
+ * Description: selects which implementation parses units.cfg lines.
+ *   - TokenChecker : the hand-rolled 'UnitPropertyCheck' + 'tokenize' state
+ *                    machine. Default; the only backend in production use.
+ *   - Grammar      : the declarative combinator-based backend above.
+ */
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParserBackend
+{
+	TokenChecker,
+	Grammar,
+}
+
+fn parse_line_with(backend: ParserBackend, line: &str) -> Result<Option<UnitProperty>, ParsePropertyError>
+{
+	match backend
+	{
+	ParserBackend::TokenChecker => parse_line(line),
+	ParserBackend::Grammar      => parse_line_grammar(line),
+	}
+}
+
+fn add_unit(database: &mut UnitDatabase, new_unit: Unit, aliases: &Vec<Symbol>)
 {
 	if new_unit.is_well_formed()
 	{
@@ -767,14 +1090,14 @@ fn add_unit(database: &mut UnitDatabase, new_unit: Unit, aliases: &Vec<Rc<String
 		{
 			println!("\n*** ERROR ***\n\
 			          Failed to add unit {}: an existing unit shares names with this one\n",
-			          unit.common_name);
+			          database.resolve(unit.common_name));
 		}
 	}
 	else
 	{
 		println!("\n*** ERROR ***\n\
 		          Failed to add unit {}: unit is missing mandatory properties.\n",
-		          new_unit.common_name);
+		          database.resolve(new_unit.common_name));
 	}
 }
 fn find_and_make_cfg() -> io::Result<File>
@@ -818,6 +1141,147 @@ fn find_and_make_cfg() -> io::Result<File>
 }
 
 pub fn load_units_list() -> Option<UnitDatabase>
+{
+	load_units_list_with_backend(ParserBackend::TokenChecker)
+}
+
+// Flushes a legacy-parsed unit into a 'units::UnitParams' and pushes it onto
+// 'migrated', mirroring the error handling fn add_unit uses for the live
+// database - a unit missing a mandatory property is reported and dropped
+// rather than aborting the whole migration.
+fn flush_migrated_unit(legacy_unit: &unit::Unit, aliases: &Vec<unit::Symbol>, symbols: &unit::SymbolTable, migrated: &mut Vec<UnitParams>)
+{
+	if !legacy_unit.is_well_formed()
+	{
+		println!("\n*** ERROR ***\n\
+		          Failed to migrate unit {}: unit is missing mandatory properties.\n",
+		          symbols.resolve(legacy_unit.common_name));
+		return;
+	}
+
+	let unit_type = UnitType::from_legacy_str(legacy_unit.unit_type)
+		.expect("legacy unit_type was already validated by fn get_unit_type");
+
+	migrated.push(UnitParams::from_legacy(
+		symbols.resolve(legacy_unit.common_name).as_ref().clone(),
+		unit_type,
+		legacy_unit.conv_factor,
+		aliases.iter().map(|&alias| symbols.resolve(alias).as_ref().clone()).collect(),
+		legacy_unit.dimensions as u32,
+		legacy_unit.zero_point,
+		legacy_unit.inverse,
+	));
+}
+
+/* migrate_legacy_config
+ *
+ * Description: migrates a legacy line-oriented 'units.cfg' at 'in_path' into
+ *   this program's current serde-based 'units.yaml' format at 'out_path'.
+ *   Runs the same parse loop as fn load_units_list_with_backend against the
+ *   old fn unit::Unit builder, flushing a unit out every time a new common
+ *   name starts the next one (see fn flush_migrated_unit), then serializes
+ *   the whole collection as YAML. Lets a user's hand-maintained units.cfg
+ *   keep working with the new loader without being rewritten by hand.
+ *
+ * Parameters:
+ *   - in_path  : path to the legacy units.cfg to read
+ *   - out_path : path the migrated units.yaml is written to
+ */
+pub fn migrate_legacy_config(in_path: &str, out_path: &str) -> Result<(), UnitDbError>
+{
+	let file = File::open(in_path)
+		.map_err(|source| UnitDbError::FileOpen { path: in_path.to_string(), source })?;
+
+	let mut legacy_cfg = BufReader::new(file);
+	let mut line = String::with_capacity(80);
+	let mut line_num = -1;
+	let mut first_unit = true;
+
+	let mut symbols = unit::SymbolTable::new();
+	let mut new_unit = unit::Unit::new();
+	let mut aliases: Vec<unit::Symbol> = Vec::new();
+	let mut migrated: Vec<UnitParams> = Vec::new();
+
+	loop
+	{
+		let bytes_read = legacy_cfg.read_line(&mut line)
+			.map_err(|source| UnitDbError::FileRead { source })?;
+
+		if bytes_read == 0
+		{
+			break;
+		}
+
+		line_num += 1;
+
+		match parse_line_with(ParserBackend::TokenChecker, &line)
+		{
+		Ok(wrapper) => {
+			if let Some(prop) = wrapper
+			{
+				match prop
+				{
+				UnitProperty::CommonName(name) => {
+					let name = symbols.intern(&name);
+
+					if first_unit
+					{
+						new_unit.set_common_name(name, &symbols);
+						first_unit = false;
+					}
+					else
+					{
+						flush_migrated_unit(&new_unit, &aliases, &symbols, &mut migrated);
+						new_unit = unit::Unit::new();
+						new_unit.set_common_name(name, &symbols);
+						aliases.clear();
+					}
+				},
+				UnitProperty::Aliases(other_names) => {
+					if new_unit.has_aliases
+					{
+						println!("\n*** WARNING ***\n\
+						          For unit {}: attempted to assign aliases twice. Ignoring this attempt.\n",
+						          symbols.resolve(new_unit.common_name));
+					}
+					else
+					{
+						new_unit.has_aliases = true;
+						aliases = other_names.iter().map(|name| symbols.intern(name)).collect();
+					}
+				},
+				UnitProperty::UnitType(unit_type)     => new_unit.set_unit_type(unit_type, &symbols),
+				UnitProperty::ConvFactor(conv_factor) => new_unit.set_conv_factor(conv_factor, &symbols),
+				UnitProperty::ZeroPoint(zero_point)   => new_unit.set_zero_point(zero_point, &symbols),
+				UnitProperty::Dimensions(dimensions)  => new_unit.set_dimensions(dimensions, &symbols),
+				UnitProperty::Inverse(inverse)        => new_unit.set_inverse(inverse, &symbols),
+				};
+			};
+		},
+		Err(err) => {
+			println!("\n*** ERROR ***\n\
+				      In line {}: \"{}\": \
+				      {}\n", line_num, line.trim_right(), err );
+		},
+		};
+
+		line.clear();
+	}
+
+	// last unit in file won't have been flushed by a following CommonName
+	flush_migrated_unit(&new_unit, &aliases, &symbols, &mut migrated);
+
+	let doc: ConfigFileUnits = migrated.into();
+	let yaml = serde_yaml::to_string(&doc)
+		.map_err(|source| UnitDbError::Serialize { source })?;
+
+	fs::write(out_path, yaml)
+		.map_err(|source| UnitDbError::FileWrite { path: out_path.to_string(), source })
+}
+
+// Same as 'load_units_list' but with the line parser explicit. See
+// 'ParserBackend'.
+pub fn load_units_list_with_backend(backend: ParserBackend) -> Option<UnitDatabase>
 {
 	let file = match find_and_make_cfg()
 	{
@@ -835,14 +1299,14 @@ pub fn load_units_list() -> Option<UnitDatabase>
 
 	let mut units_database = UnitDatabase::new();
 	let mut new_unit = Unit::new();
-	let mut aliases: Vec<Rc<String>> = Vec::new();
+	let mut aliases: Vec<Symbol> = Vec::new();
 
 
 	while units_cfg.read_line(&mut line).unwrap() > 0
 	{
 		line_num += 1;
 
-		match parse_line(&line)
+		match parse_line_with(backend, &line)
 		{
 		Ok(wrapper) => {
 			if let Some(prop) = wrapper
@@ -851,16 +1315,18 @@ pub fn load_units_list() -> Option<UnitDatabase>
 				match prop
 				{
 				UnitProperty::CommonName(name) => {
+					let name = units_database.intern(&name);
+
 					if first_unit
 					{
-						new_unit.set_common_name(name);
+						new_unit.set_common_name(name, units_database.symbols());
 						first_unit = false;
 					}
 					else
 					{
 						add_unit(&mut units_database, new_unit, &aliases);
 						new_unit = Unit::new();
-						new_unit.set_common_name(name);
+						new_unit.set_common_name(name, units_database.symbols());
 					}
 				},
 				UnitProperty::Aliases(other_names) => {
@@ -868,19 +1334,19 @@ pub fn load_units_list() -> Option<UnitDatabase>
 					{
 						println!("\n*** WARNING ***\n\
 						          For unit {}: attempted to assign aliases twice. Ignoring this attempt.\n",
-						          new_unit.common_name);
+						          units_database.resolve(new_unit.common_name));
 					}
 					else
 					{
 						new_unit.has_aliases = true;
-						aliases = other_names;
+						aliases = other_names.iter().map(|name| units_database.intern(name)).collect();
 					}
 				},
-				UnitProperty::UnitType(unit_type)     => new_unit.set_unit_type(unit_type),
-				UnitProperty::ConvFactor(conv_factor) => new_unit.set_conv_factor(conv_factor),
-				UnitProperty::ZeroPoint(zero_point)   => new_unit.set_zero_point(zero_point),
-				UnitProperty::Dimensions(dimensions)  => new_unit.set_dimensions(dimensions),
-				UnitProperty::Inverse(inverse)        => new_unit.set_inverse(inverse),
+				UnitProperty::UnitType(unit_type)     => new_unit.set_unit_type(unit_type, units_database.symbols()),
+				UnitProperty::ConvFactor(conv_factor) => new_unit.set_conv_factor(conv_factor, units_database.symbols()),
+				UnitProperty::ZeroPoint(zero_point)   => new_unit.set_zero_point(zero_point, units_database.symbols()),
+				UnitProperty::Dimensions(dimensions)  => new_unit.set_dimensions(dimensions, units_database.symbols()),
+				UnitProperty::Inverse(inverse)        => new_unit.set_inverse(inverse, units_database.symbols()),
 				};
 			};
 		},
@@ -25,18 +25,40 @@ use ::unit::UnitDatabase;
 use std::rc::Rc;
 use std::fmt;
 use std::fmt::Display;
-use ::parse::SyntaxChecker;
 use ::parse::SyntaxError;
 use ::parse::TokenType;
-use ::parse;
+use ::parse::Position;
+use ::combinator::many1;
+use ::combinator::opt;
+use ::combinator::seq;
+use ::combinator::map;
+use ::combinator::all_consuming;
 use std::error::Error;
 
 #[derive(Debug)]
 pub enum ExprParseError
 {
     Syntax(SyntaxError),
-    BadPrefix(char),
-    EmptyField(String),
+    BadPrefix(char, Position),
+    EmptyField(String, Position),
+    BadCompound(String, Position),
+}
+
+impl ExprParseError
+{
+    // Returns the character span of the offending text, for CLI callers that
+    // want to underline it with carets the way SyntaxError::render_carets
+    // does.
+    pub fn position(&self) -> Position
+    {
+        match *self
+        {
+        ExprParseError::Syntax(ref err) => Position::from(err.range()),
+        ExprParseError::BadPrefix(_, pos) => pos,
+        ExprParseError::EmptyField(_, pos) => pos,
+        ExprParseError::BadCompound(_, pos) => pos,
+        }
+    }
 }
 
 impl Error for ExprParseError
@@ -46,8 +68,9 @@ impl Error for ExprParseError
         match *self
         {
         ExprParseError::Syntax(ref err) => err.description(),
-        ExprParseError::BadPrefix(_) => "unknown metric prefix",
-        ExprParseError::EmptyField(_) => "field is empty",
+        ExprParseError::BadPrefix(..) => "unknown metric prefix",
+        ExprParseError::EmptyField(..) => "field is empty",
+        ExprParseError::BadCompound(..) => "malformed compound unit expression",
         }
     }
 
@@ -70,11 +93,14 @@ impl Display for ExprParseError
         ExprParseError::Syntax(ref err) => {
             write!(f, "{}", err)
         },
-        ExprParseError::BadPrefix(ref ch) => {
-            write!(f, "parse error: {}: \'{}\'", self.description(), ch)
+        ExprParseError::BadPrefix(ref ch, ref pos) => {
+            write!(f, "parse error @ col {}: {}: \'{}\'", pos.offset+1, self.description(), ch)
         },
-        ExprParseError::EmptyField(ref field) => {
-            write!(f, "parse error: {} {}", field, self.description())
+        ExprParseError::EmptyField(ref field, ref pos) => {
+            write!(f, "parse error @ col {}: {} {}", pos.offset+1, field, self.description())
+        },
+        ExprParseError::BadCompound(ref mesg, ref pos) => {
+            write!(f, "parse error @ col {}: {}", pos.offset+1, mesg)
         },
         }
     }
@@ -121,7 +147,10 @@ pub enum ConversionError
 {
     OutOfRange(bool),   // input or output value not a valid f64, false: input
     UnitNotFound(bool), // the unit was not found, false: input
-    TypeMismatch,       // the units' types disagree, ie volume into length
+    TypeMismatch,       // the units' SI dimension vectors disagree, ie volume into length
+    NonRatioUnit,       // an offset or inverted unit (ex. a temperature scale)
+                        // was used as one factor of a multi-factor compound
+                        // expression, which has no well-defined meaning
 }
 const INPUT: bool = false;
 const OUTPUT: bool = true;
@@ -133,6 +162,7 @@ pub enum ConversionFmt
     Short,
     Desc,
     Long,
+    Sig(u8), // round the output value to this many significant figures
 }
 
 impl Display for ConversionFmt
@@ -141,13 +171,71 @@ impl Display for ConversionFmt
     {
         match *self
         {
-        ConversionFmt::Short => write!(f, "s: short / value only"),
-        ConversionFmt::Desc => write!(f, "d: descriptive / value and output unit"),
-        ConversionFmt::Long => write!(f, "l: long / input and output values and units"),
+        ConversionFmt::Short    => write!(f, "s: short / value only"),
+        ConversionFmt::Desc     => write!(f, "d: descriptive / value and output unit"),
+        ConversionFmt::Long     => write!(f, "l: long / input and output values and units"),
+        ConversionFmt::Sig(figs) => write!(f, "g{0}: value only, rounded to {0} significant figures", figs),
         }
     }
 }
 
+// Rounds 'value' to 'sig_figs' significant figures. 0 and non-finite values
+// are returned unchanged since they have no meaningful magnitude to round
+// against.
+fn round_to_sig_figs(value: f64, sig_figs: u8) -> f64
+{
+    if value == 0.0 || !value.is_finite()
+    {
+        return value;
+    }
+
+    let sig_figs = sig_figs.max(1) as i32;
+    let magnitude = value.abs().log10().floor() as i32;
+    let scale = 10f64.powi(sig_figs - 1 - magnitude);
+
+    (value * scale).round() / scale
+}
+
+// Renders 'value' rounded to 'sig_figs' significant figures, switching to
+// scientific notation for magnitudes a fixed-point rendering would make
+// unreadable (very large or very small), the same threshold printf's "%g"
+// uses. Fixed notation is printed with exactly as many decimal places as
+// the significant figure count demands, so a trailing '0' (ex. "12.0" for
+// 3 sig figs) is preserved rather than collapsed to the integer "12", while
+// a result with no fractional digits left (ex. "12" for 2 sig figs) prints
+// as a bare integer. A value that rounds all the way down to zero (ex. too
+// few sig figs for its magnitude) still prints as "0" instead of something
+// degenerate.
+fn format_sig_figs(value: f64, sig_figs: u8) -> String
+{
+    let sig_figs = sig_figs.max(1);
+
+    if value == 0.0 || !value.is_finite()
+    {
+        return format!("{}", value);
+    }
+
+    let rounded = round_to_sig_figs(value, sig_figs);
+
+    if rounded == 0.0
+    {
+        return "0".to_string();
+    }
+
+    let magnitude = rounded.abs().log10().floor() as i32;
+
+    if magnitude < -4 || magnitude >= sig_figs as i32
+    {
+        let mantissa = rounded / 10f64.powi(magnitude);
+        format!("{:.*}e{}", (sig_figs - 1) as usize, mantissa, magnitude)
+    }
+    else
+    {
+        let decimal_places = (sig_figs as i32 - 1 - magnitude).max(0) as usize;
+        format!("{:.*}", decimal_places, rounded)
+    }
+}
+
 fn prefix_as_num(prefix: char) -> Option<f64>
 {
     let num: f64 = match prefix
@@ -179,15 +267,108 @@ fn prefix_as_num(prefix: char) -> Option<f64>
     Some(num)
 }
 
+// A single factor of a (possibly compound) unit expression, resolved down to
+// what 'convert' actually needs to do arithmetic with it: its metric prefix,
+// the alias used to look it up in the database, and the exponent it carries
+// in the expression (negative for a denominator factor).
+pub type ResolvedFactor = (char, String, i32);
+
+// Renders a factor list back into the textual form a user would type, ex.
+// '[(NO_PREFIX, "kg", 1), (NO_PREFIX, "m", 1), (NO_PREFIX, "s", -2)]' becomes
+// 'kg*m/s^2'.
+fn render_compound(factors: &Vec<ResolvedFactor>) -> String
+{
+    let mut numer = String::new();
+    let mut denom = String::new();
+
+    for &(prefix, ref alias, exponent) in factors.iter()
+    {
+        let prefix_str = if prefix != NO_PREFIX { prefix.to_string() } else { String::new() };
+        let magnitude = exponent.abs();
+        let piece = if magnitude != 1
+        {
+            format!("{}{}^{}", prefix_str, alias, magnitude)
+        }
+        else
+        {
+            format!("{}{}", prefix_str, alias)
+        };
+
+        let target = if exponent < 0 { &mut denom } else { &mut numer };
+        if !target.is_empty()
+        {
+            target.push('*');
+        }
+        target.push_str(&piece);
+    }
+
+    if denom.is_empty()
+    {
+        numer
+    }
+    else if numer.is_empty()
+    {
+        format!("1/{}", denom)
+    }
+    else
+    {
+        format!("{}/{}", numer, denom)
+    }
+}
+
+// SI symbol for each index of a 'unit::BaseDimensions' vector: length, mass,
+// time, current, temperature, amount, luminous intensity.
+const BASE_DIM_SYMBOLS: [&'static str; 7] = ["m", "kg", "s", "A", "K", "mol", "cd"];
+
+// Sums each resolved factor's SI base-dimension vector, weighted by the
+// exponent it carries, into a single vector that can be compared for
+// dimensional agreement between an input and output expression. This is
+// true SI dimensional analysis (see 'unit::base_dims_for_type'), so two
+// 'unit_type's that are dimensionally identical (ex. torque and energy, both
+// newton-meters) compare equal, and 'm*s^-1*s' and 'm' both produce the same
+// signature.
+fn dimension_signature(units: &Vec<Option<Rc<Unit>>>, factors: &Vec<ResolvedFactor>) -> [i32; 7]
+{
+    let mut sig = [0i32; 7];
+
+    for (unit, factor) in units.iter().zip(factors.iter())
+    {
+        let unit = unit.as_ref().unwrap();
+        let &(_, _, exponent) = factor;
+
+        for i in 0..sig.len()
+        {
+            sig[i] += unit.base_dims[i] as i32 * exponent;
+        }
+    }
+
+    sig
+}
+
+fn format_dimension_signature(sig: &[i32; 7]) -> String
+{
+    let parts: Vec<String> = sig.iter().enumerate()
+        .filter(|&(_, &exponent)| exponent != 0)
+        .map(|(i, &exponent)| format!("{}^{}", BASE_DIM_SYMBOLS[i], exponent))
+        .collect();
+
+    if parts.is_empty()
+    {
+        "dimensionless".to_string()
+    }
+    else
+    {
+        parts.join("*")
+    }
+}
+
 #[derive(Debug)]
 pub struct Conversion
 {
-    from_prefix: char,
-    to_prefix: char,
-    pub from_alias: String,
-    pub to_alias: String,
-    pub from: Option<Rc<Unit>>,
-    pub to: Option<Rc<Unit>>,
+    from_factors: Vec<ResolvedFactor>,
+    to_factors: Vec<ResolvedFactor>,
+    pub from: Vec<Option<Rc<Unit>>>,
+    pub to: Vec<Option<Rc<Unit>>>,
     pub input: f64,
     pub result: Result<f64, ConversionError>,
     pub format: ConversionFmt,
@@ -195,21 +376,33 @@ pub struct Conversion
 
 impl Conversion
 {
-    fn new(input_prefix: char, input_alias: String,
-        output_prefix: char, output_alias: String, input_val: f64) -> Conversion
+    fn new(from_factors: Vec<ResolvedFactor>, to_factors: Vec<ResolvedFactor>,
+        input_val: f64) -> Conversion
     {
         Conversion {
-            from_prefix: input_prefix,
-            to_prefix: output_prefix,
-            from_alias: input_alias,
-            to_alias: output_alias,
-            from: None,
-            to: None,
+            from_factors: from_factors,
+            to_factors: to_factors,
+            from: Vec::new(),
+            to: Vec::new(),
             input: input_val,
             result: Ok(1.0),
             format: ConversionFmt::Desc,
         }
     }
+
+    // Renders the input/output expressions back into the textual compound
+    // form a user would type ('kg*m/s^2'), so a caller outside this module
+    // (ex. 'Interpreter::update_recall') can remember what was actually
+    // converted without reaching into the private factor lists directly.
+    pub fn from_expr(&self) -> String
+    {
+        render_compound(&self.from_factors)
+    }
+
+    pub fn to_expr(&self) -> String
+    {
+        render_compound(&self.to_factors)
+    }
 }
 
 impl Display for Conversion
@@ -223,30 +416,13 @@ impl Display for Conversion
             {
             ConversionFmt::Short => write!(f, "{}", output),
             ConversionFmt::Desc  => {
-                let mut prefix = String::with_capacity(1);
-                if self.to_prefix != NO_PREFIX
-                {
-                    prefix.push(self.to_prefix);
-                }
-
-                write!(f, "{} {}{}", output, prefix, self.to_alias)
+                write!(f, "{} {}", output, render_compound(&self.to_factors))
             },
             ConversionFmt::Long  => {
-                let mut to_prefix = String::with_capacity(1);
-                let mut from_prefix = String::with_capacity(1);
-
-                if self.to_prefix != NO_PREFIX
-                {
-                    to_prefix.push(self.to_prefix);
-                }
-
-                if self.from_prefix != NO_PREFIX
-                {
-                    from_prefix.push(self.from_prefix);
-                }
-                write!(f, "{} {}{} = {} {}{}", self.input, from_prefix, self.from_alias,
-                    output, to_prefix, self.to_alias)
+                write!(f, "{} {} = {} {}", self.input, render_compound(&self.from_factors),
+                    output, render_compound(&self.to_factors))
             },
+            ConversionFmt::Sig(figs) => write!(f, "{}", format_sig_figs(*output, figs)),
             }
         },
         Err(ref err) => {
@@ -264,415 +440,396 @@ impl Display for Conversion
                     })
             },
             &ConversionError::UnitNotFound(in_or_out) => {
+                let (factors, resolved) = if in_or_out == OUTPUT
+                {
+                    (&self.to_factors, &self.to)
+                }
+                else
+                {
+                    (&self.from_factors, &self.from)
+                };
+
+                let missing: Vec<&str> = factors.iter().zip(resolved.iter())
+                    .filter(|&(_, unit)| unit.is_none())
+                    .map(|(factor, _)| factor.1.as_str())
+                    .collect();
+
                 write!(f, "conversion error: no unit called \'{}\' was not found",
-                    if in_or_out == OUTPUT
-                    {
-                        &self.to_alias
-                    }
-                    else
-                    {
-                        &self.from_alias
-                    })
+                    missing.join(", "))
             },
             &ConversionError::TypeMismatch =>
                 write!(f, "conversion error: input and output types differ.\
-                          \'{}\' is a {} and \'{}\' is a {}",
-                          self.from_alias, self.from.as_ref().unwrap().unit_type,
-                          self.to_alias, self.to.as_ref().unwrap().unit_type),
+                          \'{}\' is {} and \'{}\' is {}",
+                          render_compound(&self.from_factors),
+                          format_dimension_signature(&dimension_signature(&self.from, &self.from_factors)),
+                          render_compound(&self.to_factors),
+                          format_dimension_signature(&dimension_signature(&self.to, &self.to_factors))),
+            &ConversionError::NonRatioUnit =>
+                write!(f, "conversion error: offset or inverse units (ex. a temperature scale) \
+                          cannot be combined in a compound unit expression"),
             }
         },
         }
     }
 }
 
-enum NumberCheckState
+// Scans a value token the way a human would write one: strips '_' digit
+// separators, recognizes 0x/0o/0b integer prefixes, and reports what's
+// specifically wrong with a rejected token rather than a generic failure,
+// mirroring the targeted diagnostics rustc gives for malformed literals.
+fn scan_number_literal(token: &str) -> Result<f64, String>
 {
-    FloatLiteral,
-    Semicolon,
-    Trailing,
-}
-
-struct NumberCheck<'a>
-{
-    token: &'a String,
-    valid: bool,
-    state: NumberCheckState,
-}
-
-impl<'a> NumberCheck<'a>
-{
-    fn new(tok: &'a String) -> NumberCheck
+    let (negative, unsigned) = if token.starts_with('-')
     {
-        NumberCheck {
-            token: tok,
-            valid: true,
-            state: NumberCheckState::FloatLiteral, 
-        }
+        (true, &token[1..])
     }
-}
-
-impl<'a> SyntaxChecker for NumberCheck<'a>
-{
-    fn feed_token(&mut self, token: &str, delim: bool, index: usize) -> bool
+    else if token.starts_with('+')
     {
-        if self.valid
-        {
-            match self.state
-            {
-            NumberCheckState::FloatLiteral if !delim => {
-                if token.is_empty()
-                {
-                    self.state = NumberCheckState::Semicolon;
-                }
-                else if token.parse::<f64>().is_ok()
-                {
-                    self.state = NumberCheckState::Trailing;
-                }
-                else
-                {
-                    self.valid = false;
-                }
-            },
-            NumberCheckState::Semicolon if delim => {
-                if token == ";"
-                {
-                    self.state = NumberCheckState::Trailing;
-                }
-                else
-                {
-                    self.valid = false;
-                }
-            },
-            
-            NumberCheckState::Trailing => {
-                if !token.is_empty()
-                {
-                    self.valid = false;
-                }
-            },
-            _ => unreachable!("number syntax check reached impossible state"),
-            };
-        }
-        
-        self.valid
+        (false, &token[1..])
     }
-
-    fn is_esc(&self, ch: char) -> bool
+    else
     {
-        false // no escape sequences allowed for numbers
-    }
+        (false, token)
+    };
 
-    fn is_comment(&self, ch: char) -> bool
-    {
-        ch == '#'
-    }
+    let cleaned: String = unsigned.chars().filter(|&ch| ch != '_').collect();
 
-    fn is_delim(&self, ch: char) -> bool
+    let radix = if cleaned.starts_with("0x") || cleaned.starts_with("0X")
     {
-        ch == ';'
+        Some((16, "hexadecimal"))
     }
-
-    fn is_preserved_delim(&self, ch: char) -> bool
+    else if cleaned.starts_with("0o") || cleaned.starts_with("0O")
     {
-        false
+        Some((8, "octal"))
     }
-
-    fn esc_char(&self) -> char
+    else if cleaned.starts_with("0b") || cleaned.starts_with("0B")
     {
-        '\\' // dummy. actually no esc sequence.
+        Some((2, "binary"))
     }
-
-    fn valid(&self) -> bool
+    else
     {
-        self.valid
-    }
+        None
+    };
 
-    fn assert_valid(&self, index: usize, more_tokens: bool) -> Result<(), SyntaxError>
+    if let Some((base, name)) = radix
     {
-        if !more_tokens || !self.valid
+        if cleaned.contains('.')
         {
-            match self.state
-            {
-            NumberCheckState::FloatLiteral => {
-                // reached when receiving a non
-                return Err(SyntaxError::Expected(index, "float literal".to_string()));
-            },
-            NumberCheckState::Semicolon => {
-                // not okay to exit without receiving a recall expression
-                // not okay to exit without receiving anything
-                return Err(SyntaxError::Expected(index, "float literal or recall expression".to_string()));
-            },
-            _ => (),
-            };
+            return Err(format!("{} float literals are not supported", name));
         }
 
-        if !self.valid{
-            match self.state
-            {
-            NumberCheckState::Trailing => {
-                return Err(SyntaxError::Expected(index, "nothing after value expression".to_string()));
-            },
-            _ => (),
-            };
-        }
-        
-        Ok(())
+        return match i64::from_str_radix(&cleaned[2..], base)
+        {
+            Ok(num) => Ok(if negative { -(num as f64) } else { num as f64 }),
+            Err(_) => Err(format!("invalid digit for base {}", base)),
+        };
     }
 
-    fn esc_set(&self) -> bool
+    if cleaned.starts_with('.')
     {
-        false
+        return Err("float literal requires an integer part".to_string());
     }
 
-    fn set_esc(&mut self, set: bool)
-    {
-        
-    }
+    let signed = if negative { format!("-{}", cleaned) } else { cleaned };
 
-    fn reset(&mut self)
-    {
-        self.valid = true;
-        self.state = NumberCheckState::FloatLiteral;
-    }
+    signed.parse::<f64>().map_err(|_| "float literal".to_string())
 }
 
-pub struct NumberExpr
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExprOp
 {
-    pub value: f64,
-    pub recall: bool,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
 }
 
-pub fn parse_number_expr(token: &String) -> Result<NumberExpr, ExprParseError>
+#[derive(Debug, Clone, Copy)]
+enum ExprTok
 {
-    let mut number_check = NumberCheck::new(token);
-    // if the syntax check passed, you know you are either getting a semicolon or a float literal
-    let mut tokens: Vec<TokenType> = try!(parse::tokenize(token, &mut number_check));
-    tokens.retain(|tok| !tok.is_empty());
-    
-    if tokens.len() < 1
+    Number(f64),
+    Op(ExprOp),
+    LParen,
+    RParen,
+}
+
+// Binds tighter than any binary operator so that a unary +/- only ever
+// grabs the single primary that follows it rather than an entire
+// sub-expression.
+const UNARY_BP: u8 = 4;
+
+// Left/right binding powers for precedence-climbing. '+' and '-' bind
+// loosest, '*' and '/' bind tighter, and '^' binds tightest of the binary
+// operators. All but '^' are left-associative (right bp = left bp + 1,
+// so a same-precedence operator to the right is left for the enclosing
+// loop rather than pulled into this operator's right-hand side); '^' is
+// right-associative (right bp equal to left bp).
+fn binding_power(op: ExprOp) -> (u8, u8)
+{
+    match op
     {
-        return Err(
-            ExprParseError::from(
-                SyntaxError::Expected(0, "float literal or recall expression".to_string())));
+    ExprOp::Add => (1, 2),
+    ExprOp::Sub => (1, 2),
+    ExprOp::Mul => (2, 3),
+    ExprOp::Div => (2, 3),
+    ExprOp::Pow => (3, 3),
     }
-    
-    let mut value_expr = NumberExpr {
-        value: -1.0,
-        recall: false,
-    };
-    
-    for (index, tok) in tokens.drain(..).enumerate()
-    {
-        if index > 0
-        {
-            unreachable!("too many tokens in value expression after syntax check");
-        }
+}
 
-        match tok
+// Splits a value expression into numbers, the binary operators '+ - * / ^',
+// and parentheses. A number token is the maximal run of alphanumeric,
+// '.', and '_' characters, so digit separators and radix prefixes reach
+// 'scan_number_literal' unmodified; a leading sign is never part of a
+// number token here since unary +/- are handled by the parser instead.
+fn lex_expr(text: &str) -> Result<Vec<ExprTok>, String>
+{
+    let chars: Vec<char> = text.chars().collect();
+    let mut toks = Vec::with_capacity(chars.len());
+    let mut i = 0;
+
+    while i < chars.len()
+    {
+        match chars[i]
         {
-        TokenType::Normal(number) => {
-            value_expr.value = match number.parse::<f64>()
+        '+' => { toks.push(ExprTok::Op(ExprOp::Add)); i += 1; },
+        '-' => { toks.push(ExprTok::Op(ExprOp::Sub)); i += 1; },
+        '*' => { toks.push(ExprTok::Op(ExprOp::Mul)); i += 1; },
+        '/' => { toks.push(ExprTok::Op(ExprOp::Div)); i += 1; },
+        '^' => { toks.push(ExprTok::Op(ExprOp::Pow)); i += 1; },
+        '(' => { toks.push(ExprTok::LParen); i += 1; },
+        ')' => { toks.push(ExprTok::RParen); i += 1; },
+        ch if ch.is_alphanumeric() || ch == '.' || ch == '_' => {
+            let start = i;
+
+            while i < chars.len() &&
+                  (chars[i].is_alphanumeric() || chars[i] == '.' || chars[i] == '_')
             {
-            Ok(num) => num,
-            Err(err) => {
-                unreachable!("float literal cannot be parsed as such after syntax check");
-            },
-            };
-        },
-        TokenType::Delim(delim) => {
-            if delim == ";"
-            {
-                value_expr.recall = true;
-            }
-            else
-            {
-                unreachable!("illegal value recall character after syntax check");
+                i += 1;
             }
+
+            let literal: String = chars[start..i].iter().collect();
+            toks.push(ExprTok::Number(try!(scan_number_literal(&literal))));
         },
+        ch => return Err(format!("unexpected character \'{}\' in value expression", ch)),
         };
     }
-    
-    Ok(value_expr)
-}
 
-
-enum UnitCheckState
-{
-    NameOrExpr,
-    UnderscoreOrColon,
-    PrefixOrName,
-    Colon,
-    Trailing,
+    Ok(toks)
 }
 
-
-struct UnitCheck
+// Walks a token stream produced by 'lex_expr' with precedence-climbing
+// (Pratt) parsing, the same shape as rustc's AssocOp/Fixity machinery,
+// folding each operator into a running f64 as soon as both of its
+// operands are known.
+struct ExprParser<'a>
 {
-    esc_seq: bool,
-    valid: bool,
-    state: UnitCheckState,
+    toks: &'a [ExprTok],
+    pos: usize,
 }
 
-impl UnitCheck
+impl<'a> ExprParser<'a>
 {
-    fn new() -> UnitCheck
+    fn new(toks: &'a [ExprTok]) -> ExprParser<'a>
+    {
+        ExprParser { toks: toks, pos: 0 }
+    }
+
+    fn bump(&mut self) -> Option<ExprTok>
     {
-        UnitCheck {
-            esc_seq: false,
-            valid: true,
-            state: UnitCheckState::NameOrExpr,
+        let tok = self.toks.get(self.pos).cloned();
+
+        if tok.is_some()
+        {
+            self.pos += 1;
         }
+
+        tok
     }
-}
 
-impl SyntaxChecker for UnitCheck
-{
-    fn feed_token(&mut self, token: &str, delim: bool, index: usize) -> bool
+    // Reads a single primary: a number, a parenthesized sub-expression, or
+    // a unary +/- applied to another primary.
+    fn parse_primary(&mut self) -> Result<f64, String>
     {
-        if self.valid
+        match self.bump()
         {
-            match self.state
+        Some(ExprTok::Number(num)) => Ok(num),
+        Some(ExprTok::LParen) => {
+            let inner = try!(self.parse_expr(0));
+
+            match self.bump()
             {
-            UnitCheckState::NameOrExpr if !delim => {
-                if token.is_empty()
-                {
-                    self.state = UnitCheckState::UnderscoreOrColon;
-                }
-                else
-                {
-                    self.state = UnitCheckState::Trailing;
-                }
-            },
-            UnitCheckState::UnderscoreOrColon if delim => {
-                if token == "_"
-                {
-                    self.state = UnitCheckState::PrefixOrName;
-                }
-                else if token == ":"
-                {
-                    self.state = UnitCheckState::Trailing;
-                }
-                else
-                {
-                    self.valid = false;
-                }
-            },
-            UnitCheckState::PrefixOrName if !delim => {
-                if token.is_empty()
-                {
-                    self.valid = false;
-                }
-                else if token.len() < 2
-                {
-                    self.state = UnitCheckState::Colon;
-                }
-                else
-                {
-                    self.state = UnitCheckState::Trailing;
-                }
-            },
-            UnitCheckState::Colon if delim => {
-                if token == ":"
-                {
-                    self.state = UnitCheckState::Trailing;
-                }
-                else
-                {
-                    self.valid = false;
-                }
-            },
-            UnitCheckState::Trailing => {
-                if !token.is_empty()
+            Some(ExprTok::RParen) => Ok(inner),
+            _ => Err("mismatched parentheses in value expression".to_string()),
+            }
+        },
+        Some(ExprTok::Op(ExprOp::Sub)) => Ok(-try!(self.parse_expr(UNARY_BP))),
+        Some(ExprTok::Op(ExprOp::Add)) => self.parse_expr(UNARY_BP),
+        Some(ExprTok::RParen) => Err("mismatched parentheses in value expression".to_string()),
+        Some(ExprTok::Op(_)) | None => Err("trailing operator in value expression".to_string()),
+        }
+    }
+
+    // Reads a primary, then loops consuming any operator whose left
+    // binding power is at least 'min_bp', recursing with that operator's
+    // right binding power to parse the right-hand operand before folding
+    // the two together.
+    fn parse_expr(&mut self, min_bp: u8) -> Result<f64, String>
+    {
+        let mut lhs = try!(self.parse_primary());
+
+        loop
+        {
+            let op = match self.toks.get(self.pos)
+            {
+            Some(&ExprTok::Op(op)) => op,
+            _ => break,
+            };
+
+            let (left_bp, right_bp) = binding_power(op);
+
+            if left_bp < min_bp
+            {
+                break;
+            }
+
+            self.pos += 1;
+            let rhs = try!(self.parse_expr(right_bp));
+
+            lhs = match op
+            {
+            ExprOp::Add => lhs + rhs,
+            ExprOp::Sub => lhs - rhs,
+            ExprOp::Mul => lhs * rhs,
+            ExprOp::Div => {
+                if rhs == 0.0
                 {
-                    self.valid = false;
+                    return Err("division by zero".to_string());
                 }
+
+                lhs / rhs
             },
-            _ => unreachable!("unit expression syntax check reached impossible state"),
+            ExprOp::Pow => lhs.powf(rhs),
             };
         }
-        
-        self.valid
-    }
-    
-    fn is_esc(&self, ch: char) -> bool
-    {
-        ch == '\\'
-    }
-    
-    fn is_comment(&self, ch: char) -> bool
-    {
-        false
-    }
-    
-    fn is_delim(&self, ch: char) -> bool
-    {
-        ch == '_' ||
-        ch == ':'
+
+        Ok(lhs)
     }
-    
-    fn is_preserved_delim(&self, ch: char) -> bool
+}
+
+// Evaluates a value-position expression such as '2+3/4' or '(5*2)' down to
+// a single f64, in place of the plain float literal 'scan_number_literal'
+// alone accepts. See 'ExprParser' for the precedence-climbing parse; it
+// already covers the full '+ - * / ^', parens, and unary-minus grammar, so
+// there's no separate shunting-yard pass to maintain alongside it.
+fn eval_number_expr(token: &str) -> Result<f64, String>
+{
+    let toks = try!(lex_expr(token));
+
+    if toks.is_empty()
     {
-        false
+        return Err("float literal".to_string());
     }
-    
-    fn esc_char(&self) -> char
+
+    let mut parser = ExprParser::new(&toks);
+    let value = try!(parser.parse_expr(0));
+
+    if parser.pos < toks.len()
     {
-        '\\'
+        return match toks[parser.pos]
+        {
+        ExprTok::RParen => Err("mismatched parentheses in value expression".to_string()),
+        _ => Err("trailing operator in value expression".to_string()),
+        };
     }
-    
-    fn valid(&self) -> bool
+
+    // catches overflow and invalid operations (ex. a fractional power of a
+    // negative base) that 'parse_expr' can't reject on its own since it only
+    // folds one operator at a time
+    if !value.is_finite()
     {
-        self.valid
+        return Err("value expression did not evaluate to a finite number".to_string());
     }
-    
-    fn assert_valid(&self, index: usize, more_tokens: bool) -> Result<(), SyntaxError>
-    {
-        if !more_tokens || !self.valid
+
+    Ok(value)
+}
+
+// Describes how a value expression's previous-result reference, if any,
+// should be resolved against the interpreter's value history.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Recall
+{
+    None,      // plain literal, no recall
+    Last,      // ';' - the most recent result
+    Back(u32), // ';;' chained N times - the Nth previous result
+    Index(u32),// ';N' - an explicit absolute index into the history
+}
+
+pub struct NumberExpr
+{
+    pub value: f64,
+    pub recall: Recall,
+}
+
+fn is_semicolon(ch: char) -> bool { ch == ';' }
+fn is_digit(ch: char) -> bool { ch.is_digit(10) }
+
+// Parses a run of digits as the explicit recall index in ';N', failing with
+// the same message the old hand-rolled checker raised on overflow.
+fn recall_index<'p>() -> ::combinator::Parser<'p, u32>
+{
+    Box::new(|input: &str, offset: usize| {
+        let (rest, new_offset, digits) = try!(many1("integer recall depth", is_digit)(input, offset));
+
+        match digits.parse::<u32>()
         {
-            match self.state
-            {
-            UnitCheckState::NameOrExpr | UnitCheckState::UnderscoreOrColon => {
-                return Err(SyntaxError::Expected(index,
-                        "unit name or recall expression".to_string()));
-            },
-            UnitCheckState::PrefixOrName | UnitCheckState::Colon => {
-                return Err(SyntaxError::Expected(index, 
-                        "metric prefix together with unit name / recall expression".to_string()));
-            },
-            _ => (),
-            };
+        Ok(index) => Ok((rest, new_offset, index)),
+        Err(_) => Err(SyntaxError::Expected(offset..new_offset, "integer recall depth".to_string())),
         }
-        
-        if !self.valid
-        {
-            match self.state
+    })
+}
+
+// Parses a recall expression: one or more ';' chained together, optionally
+// followed directly by an explicit index ('N' in ';N').
+fn recall_chain<'p>() -> ::combinator::Parser<'p, Recall>
+{
+    map(
+        seq(many1("recall expression", is_semicolon), opt(recall_index())),
+        |(semis, index): (String, Option<u32>)| {
+            let semicolons = semis.chars().count() as u32;
+
+            match (semicolons, index)
             {
-            UnitCheckState::Trailing => {
-                return Err(SyntaxError::Expected(index,
-                        "no trailing expressions after unit name".to_string()));
-            },
-            _ => (),
-            };
-        }
-        
-        Ok(())
-    }
-    
-    fn esc_set(&self) -> bool
+            (_, Some(n)) => Recall::Index(n),
+            (1, None)    => Recall::Last,
+            (n, None)    => Recall::Back(n),
+            }
+        })
+}
+
+// Parses a value expression: either an arithmetic expression understood by
+// 'eval_number_expr', or a recall expression referencing a previous result.
+// Unlike the hand-rolled checker it replaces, this doesn't collect every
+// error it finds along the way; a value expression only ever has one field
+// to fail on, and 'to_conv_primitive' only ever looked at the first error
+// anyway.
+pub fn parse_number_expr(token: &String) -> Result<NumberExpr, SyntaxError>
+{
+    if token.is_empty()
     {
-        self.esc_seq
+        return Err(SyntaxError::Expected(0..0, "float literal or recall expression".to_string()));
     }
-    
-    fn set_esc(&mut self, set: bool)
+
+    if token.starts_with(';')
     {
-        self.esc_seq = set;
+        let (_, _, recall) = try!(all_consuming(recall_chain(), "nothing after value expression")(token, 0));
+        return Ok(NumberExpr { value: -1.0, recall: recall });
     }
-    
-    fn reset(&mut self)
+
+    match eval_number_expr(token)
     {
-        self.valid = true;
-        self.state = UnitCheckState::NameOrExpr;
-        self.esc_seq = false;
+    Ok(value) => Ok(NumberExpr { value: value, recall: Recall::None }),
+    Err(mesg) => Err(SyntaxError::Expected(0..token.chars().count(), mesg)),
     }
 }
 
@@ -684,84 +841,283 @@ pub struct UnitExpr
     pub recall: bool,
 }
 
+// Reads characters up to (but not including) an unescaped '_' or ':' or the
+// end of input - the same span 'parse::tokenize' would carve out as the next
+// Normal token given those two characters as delimiters. '\' escapes a
+// literal '_', ':', or '\' so an alias may contain them. Never fails; a run
+// of zero chars just yields an empty String, leaving it to the caller to
+// decide whether that's acceptable.
+fn unit_text<'p>() -> ::combinator::Parser<'p, String>
+{
+    Box::new(|input: &str, offset: usize| {
+        let chars: Vec<char> = input.chars().collect();
+        let mut text = String::new();
+        let mut i = 0;
+
+        while i < chars.len()
+        {
+            if chars[i] == '\\' && i + 1 < chars.len() &&
+                (chars[i+1] == '_' || chars[i+1] == ':' || chars[i+1] == '\\')
+            {
+                text.push(chars[i+1]);
+                i += 2;
+            }
+            else if chars[i] == '_' || chars[i] == ':'
+            {
+                break;
+            }
+            else
+            {
+                text.push(chars[i]);
+                i += 1;
+            }
+        }
+
+        let consumed_bytes: usize = chars[..i].iter().map(|ch| ch.len_utf8()).sum();
+        Ok((&input[consumed_bytes..], offset + i, text))
+    })
+}
+
+// Parses a single unit expression: a plain alias ('kg'), a prefixed alias
+// ('_km'), a bare recall (':'), or a prefixed recall ('_k:'). A metric
+// prefix is always introduced by a leading '_' and is exactly the character
+// immediately following it; anything past that is the alias, unless it's
+// just a ':' meaning "recall, scaled by this prefix" instead.
 pub fn parse_unit_expr(token: &String) -> Result<UnitExpr, ExprParseError>
 {
-    let mut expr_checker = UnitCheck::new();
-    let mut tokens: Vec<TokenType> = try!(parse::tokenize(token, &mut expr_checker));
-    tokens.retain(|tok| !tok.is_empty());
-    
-    if tokens.len() < 1
+    if token.is_empty()
     {
-        return Err(ExprParseError::from(SyntaxError::Expected(0,
-                "metric prefix together with unit name / recall expression".to_string())));
+        return Err(ExprParseError::from(SyntaxError::Expected(0..0,
+                "unit name or recall expression".to_string())));
     }
-    
-    let mut unit_expr = UnitExpr {
-        prefix: NO_PREFIX,
-        alias: None,
-        recall: false,
-    };
 
-    let mut tokens_iter = tokens.drain(..);
+    if token == ":"
+    {
+        return Ok(UnitExpr { prefix: NO_PREFIX, alias: None, recall: true });
+    }
 
-    match tokens_iter.next().unwrap()
+    let mut unit_expr = UnitExpr { prefix: NO_PREFIX, alias: None, recall: false };
+
+    if token.starts_with('_')
     {
-    TokenType::Delim(ref delim) if delim == "_" => {
-        let mut alias = tokens_iter.next().unwrap().unwrap();
-        let mut new_alias = String::with_capacity(alias.len() - 1);
-        let mut alias_iter = alias.chars();
-        let prefix = alias_iter.next().unwrap();
+        let (rest, offset, text) = unit_text()(&token[1..], 1).unwrap();
+
+        if text.is_empty()
+        {
+            return Err(ExprParseError::from(SyntaxError::Expected(1..2,
+                    "metric prefix together with unit name / recall expression".to_string())));
+        }
+
+        let mut text_chars = text.chars();
+        let prefix = text_chars.next().unwrap();
+        let alias: String = text_chars.collect();
 
         if prefix_as_num(prefix).is_none()
         {
-            return Err(ExprParseError::BadPrefix(prefix));
+            return Err(ExprParseError::BadPrefix(prefix, Position::new(1, text.len())));
         }
-        
+
         unit_expr.prefix = prefix;
-        
-        if let Some(trailing) = tokens_iter.next()
+
+        if alias.is_empty()
         {
-            match trailing
+            if !rest.starts_with(':')
             {
-            TokenType::Delim(ref colon) if colon == ":" => {
-                unit_expr.recall = true;
-            },
-            _ => unreachable!("illegal delimiter in unit expression after syntax check"),
-            };
+                return Err(ExprParseError::from(SyntaxError::Expected(offset..(offset+1),
+                        "metric prefix together with unit name / recall expression".to_string())));
+            }
+
+            unit_expr.recall = true;
+
+            if rest.len() > 1
+            {
+                return Err(ExprParseError::from(SyntaxError::Expected((offset+1)..(offset+2),
+                        "no trailing expressions after unit name".to_string())));
+            }
+        }
+        else if !rest.is_empty()
+        {
+            return Err(ExprParseError::from(SyntaxError::Expected(offset..(offset+1),
+                    "no trailing expressions after unit name".to_string())));
         }
         else
         {
-            for ch in alias_iter
-            {
-                new_alias.push(ch);
-            }
-            
-            unit_expr.alias = Some(new_alias);
+            unit_expr.alias = Some(alias);
         }
-        
-        if tokens_iter.next().is_some()
+    }
+    else
+    {
+        let (rest, offset, alias) = unit_text()(token, 0).unwrap();
+
+        if !rest.is_empty()
         {
-            unreachable!("extra tokens in unit expression after syntax check");
+            return Err(ExprParseError::from(SyntaxError::Expected(offset..(offset+1),
+                    "no trailing expressions after unit name".to_string())));
         }
-    },
-    TokenType::Delim(ref delim) if delim == ":" => {
-        unit_expr.recall = true;
-    },
-    TokenType::Normal(alias) => {
+
         unit_expr.alias = Some(alias);
-    },
-    _ => unreachable!("unexpected token begins unit expression"),
-    };
+    }
 
     Ok(unit_expr)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UnitOp
+{
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone)]
+enum UnitTok
+{
+    Factor(String),
+    Op(UnitOp),
+    Exponent(i32),
+}
+
+// Lexes a compound unit expression ('kg*m/s^2') into factor substrings and
+// the '*' / '/' / '^int' operators joining them, maximal-munch style like
+// 'lex_expr' above: anything that isn't an operator character is folded into
+// the next factor token and handed to 'parse_unit_expr' unexamined, so every
+// trick a single unit expression supports (metric prefixes, recall) still
+// works inside one factor of a compound expression.
+fn lex_unit_expr(text: &str) -> Result<Vec<UnitTok>, String>
+{
+    let chars: Vec<char> = text.chars().collect();
+    let mut toks = Vec::new();
+    let mut i = 0;
+
+    while i < chars.len()
+    {
+        match chars[i]
+        {
+        '*' => { toks.push(UnitTok::Op(UnitOp::Mul)); i += 1; },
+        '/' => { toks.push(UnitTok::Op(UnitOp::Div)); i += 1; },
+        '^' => {
+            i += 1;
+            let start = i;
+
+            if i < chars.len() && chars[i] == '-'
+            {
+                i += 1;
+            }
+            while i < chars.len() && chars[i].is_digit(10)
+            {
+                i += 1;
+            }
+
+            let digits = &chars[start..i];
+            if digits.is_empty() || (digits.len() == 1 && digits[0] == '-')
+            {
+                return Err("expected an integer exponent after \'^\'".to_string());
+            }
+
+            let exp_str: String = digits.iter().cloned().collect();
+            toks.push(UnitTok::Exponent(exp_str.parse::<i32>().unwrap()));
+        },
+        _ => {
+            let start = i;
+            while i < chars.len() && chars[i] != '*' && chars[i] != '/' && chars[i] != '^'
+            {
+                i += 1;
+            }
+            toks.push(UnitTok::Factor(chars[start..i].iter().cloned().collect()));
+        },
+        };
+    }
+
+    Ok(toks)
+}
+
+// Parses a derived-unit expression: a product/quotient of prefixed base
+// units with optional integer exponents ('N*m', 'kg/s^2', 'W/m^2'). The
+// result is the flattened list of numerator/denominator factors the
+// conversion routine multiplies and divides through; a denominator factor
+// (from '/') or a negative '^' exponent both just negate the factor's
+// exponent, so 'm/s' and 'm*s^-1' parse to the same thing. A bare unit with
+// no operators degrades to a single factor with exponent 1, so this is a
+// strict superset of the old single-unit grammar.
+pub fn parse_compound_unit_expr(token: &String) -> Result<Vec<(UnitExpr, i32)>, ExprParseError>
+{
+    let toks = match lex_unit_expr(token)
+    {
+        Ok(toks) => toks,
+        Err(mesg) => return Err(ExprParseError::BadCompound(mesg, Position::new(0, token.len()))),
+    };
+
+    if toks.is_empty()
+    {
+        return Err(ExprParseError::from(SyntaxError::Expected(0..0,
+                "metric prefix together with unit name / recall expression".to_string())));
+    }
+
+    let mut factors: Vec<(UnitExpr, i32)> = Vec::new();
+    let mut op = UnitOp::Mul;
+    let mut expect_factor = true;
+    let mut toks_iter = toks.into_iter().peekable();
+
+    while let Some(tok) = toks_iter.next()
+    {
+        match tok
+        {
+        UnitTok::Factor(text) if expect_factor => {
+            let unit_expr = try!(parse_unit_expr(&text));
+            let mut exponent = if op == UnitOp::Div { -1 } else { 1 };
+
+            if let Some(&UnitTok::Exponent(exp)) = toks_iter.peek()
+            {
+                toks_iter.next();
+                exponent *= exp;
+            }
+
+            factors.push((unit_expr, exponent));
+            expect_factor = false;
+        },
+        UnitTok::Op(new_op) if !expect_factor => {
+            op = new_op;
+            expect_factor = true;
+        },
+        _ => {
+            return Err(ExprParseError::BadCompound(
+                    "malformed compound unit expression".to_string(),
+                    Position::new(0, token.len())));
+        },
+        };
+    }
+
+    if expect_factor
+    {
+        return Err(ExprParseError::BadCompound(
+                "expected a unit factor after trailing \'*\' or \'/\'".to_string(),
+                Position::new(0, token.len())));
+    }
+
+    Ok(factors)
+}
 
 pub struct ConvPrimitive
 {
     pub input_vals: Vec<NumberExpr>,
-    pub input_unit: UnitExpr,
-    pub output_units: Vec<UnitExpr>,
+    pub input_unit: Vec<(UnitExpr, i32)>,
+    pub output_units: Vec<Vec<(UnitExpr, i32)>>,
+}
+
+// Holds the partial result 'to_conv_primitive' managed to assemble together
+// with every error it hit along the way, rustc-diagnostics-vector style,
+// rather than forcing the caller to fix and re-run one field at a time.
+pub struct ParseReport
+{
+    pub primitive: ConvPrimitive,
+    pub errors: Vec<GeneralParseError>,
+}
+
+impl ParseReport
+{
+    pub fn is_ok(&self) -> bool
+    {
+        self.errors.is_empty()
+    }
 }
 
 /* Enum for the state matchine of the to_conv_primitive function.
@@ -779,29 +1135,36 @@ enum ConvPrimState
  * and converts this line into a Number and Unit Exprs for convient use later
  * in the program. Acts as an intermediary to filter out syntax errors before
  * they reach the main conversion routines.
- * 
+ *
+ * Unlike a single bad field aborting the whole line, a token that fails to
+ * parse for whatever role the state machine currently expects is recorded
+ * as a 'GeneralParseError' and skipped so the next whitespace-delimited
+ * token gets a chance to fill that same role; the state machine otherwise
+ * keeps running to the end of the line so every problem surfaces in one
+ * pass.
+ *
  * Paramters:
  *   tokens - line tokenized at spaces given as Vec<TokenType>
- * 
- * Returns: Result<>
- *   Ok(ConvPrimitve) - the line converted to expressions
- *   Error(ExprParseError) - error if any occured
+ *
+ * Returns:
+ *   ParseReport - the expressions successfully parsed so far, plus every
+ *                 'GeneralParseError' hit along the way. 'ParseReport::is_ok'
+ *                 tells you whether that error list is empty.
  */
-pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, GeneralParseError>
+pub fn to_conv_primitive(tokens: &Vec<TokenType>) -> ParseReport
 {
     let mut value_exprs: Vec<NumberExpr> = Vec::new(); //NumberExpr { value: 0.0, recall: false };
-    let mut unit_in_expr = UnitExpr { prefix: NO_PREFIX,
-                                      alias: None,
-                                      recall: false };
-    let mut unit_out_exprs: Vec<UnitExpr> = Vec::new();
-    
+    let mut unit_in_expr: Vec<(UnitExpr, i32)> = Vec::new();
+    let mut unit_out_exprs: Vec<Vec<(UnitExpr, i32)>> = Vec::new();
+    let mut errors: Vec<GeneralParseError> = Vec::new();
+
     let mut state = ConvPrimState::GetValueExpr;
-    
+
     for (index, token) in tokens.iter().enumerate()
     {
         let expr = match token
         {
-            &TokenType::Delim(_) =>
+            &TokenType::Delim(..) =>
             {
                 unreachable!("conversion primitive generator was given unsanitary input. delimiter detected");
             },
@@ -820,13 +1183,13 @@ pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, G
                         Ok(new_value_expr) => {
                             value_exprs.push(new_value_expr);
                             state = ConvPrimState::GetMoreValueExpr;
-                            reuse_token = false;
                         },
                         Err(expr_parse_err) => {
-                            return Err(GeneralParseError { err: expr_parse_err,
+                            errors.push(GeneralParseError { err: ExprParseError::from(expr_parse_err),
                                 failed_at: index });
                         }
                     };
+                    reuse_token = false;
                 },
                 ConvPrimState::GetMoreValueExpr => {
                     match parse_number_expr(expr)
@@ -835,41 +1198,43 @@ pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, G
                                 value_exprs.push(new_value_expr);
                                 reuse_token = false;
                             },
+                            // not an error: running out of value exprs just means
+                            // this token is the input unit expr instead
                             Err(expr_parse_err) => state = ConvPrimState::GetInputExpr,
                         };
                 },
                 ConvPrimState::GetInputExpr => {
-                    unit_in_expr = match parse_unit_expr(expr)
+                    match parse_compound_unit_expr(expr)
                     {
-                        Ok(new_unit_expr) => new_unit_expr,
+                        Ok(new_unit_expr) => {
+                            unit_in_expr = new_unit_expr;
+                            state = ConvPrimState::GetOutputExpr;
+                        },
                         Err(parse_err) => {
-                            return Err(GeneralParseError { err: parse_err,
-                                failed_at: index });
+                            errors.push(GeneralParseError { err: parse_err, failed_at: index });
                         }
                     };
-
-                    state = ConvPrimState::GetOutputExpr;
                     reuse_token = false;
                 },
                 ConvPrimState::GetOutputExpr => {
-                    match parse_unit_expr(expr)
+                    match parse_compound_unit_expr(expr)
                     {
-                        Ok(new_unit_expr) => unit_out_exprs.push(new_unit_expr),
+                        Ok(new_unit_expr) => {
+                            unit_out_exprs.push(new_unit_expr);
+                            state = ConvPrimState::GetMoreOutput;
+                        },
                         Err(parse_err) => {
-                            return Err(GeneralParseError { err: parse_err,
-                                failed_at: index });
+                            errors.push(GeneralParseError { err: parse_err, failed_at: index });
                         }
                     };
-                    state = ConvPrimState::GetMoreOutput;
                     reuse_token = false;
                 },
                 ConvPrimState::GetMoreOutput => {
-                    match parse_unit_expr(expr)
+                    match parse_compound_unit_expr(expr)
                     {
                         Ok(new_unit_expr) => unit_out_exprs.push(new_unit_expr),
                         Err(parse_err) => {
-                            return Err(GeneralParseError { err: parse_err,
-                                failed_at: index });
+                            errors.push(GeneralParseError { err: parse_err, failed_at: index });
                         }
                     };
                     reuse_token = false;
@@ -881,41 +1246,112 @@ pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, G
             };
         }
     }
-    
-    Ok(ConvPrimitive { input_vals: value_exprs,
-                       input_unit: unit_in_expr,
-                       output_units: unit_out_exprs })
+
+    ParseReport {
+        primitive: ConvPrimitive { input_vals: value_exprs,
+                                   input_unit: unit_in_expr,
+                                   output_units: unit_out_exprs },
+        errors: errors,
+    }
+}
+
+
+// Performs the original scalar conversion: scale by the prefix, invert if
+// the unit is defined that way (ex. mpg), convert to base units, shift by
+// the zero point (ex. degC -> K), convert to the target units, invert back,
+// then scale by the target prefix. This is the exact algorithm 'convert'
+// used before compound expressions existed, preserved verbatim for the
+// overwhelmingly common one-unit-in, one-unit-out case.
+fn convert_simple(input: f64, from: &ResolvedFactor, from_unit: &Unit,
+    to: &ResolvedFactor, to_unit: &Unit) -> f64
+{
+    let &(from_prefix, _, _) = from;
+    let &(to_prefix, _, _) = to;
+
+    // S1
+    let mut output_val = input * prefix_as_num(from_prefix).unwrap().powi(from_unit.dimensions as i32);
+
+    // S2
+    if from_unit.inverse
+    {
+        output_val = 1.0 / output_val;
+    }
+
+    output_val *= from_unit.conv_factor; // S3
+    output_val += from_unit.zero_point - to_unit.zero_point; // S4
+    output_val /= to_unit.conv_factor; // S5
+
+    // S6
+    if to_unit.inverse
+    {
+        output_val = 1.0 / output_val;
+    }
+
+    // S7
+    output_val / prefix_as_num(to_prefix).unwrap().powi(to_unit.dimensions as i32)
 }
 
+// Folds one side of a compound expression down to a single coefficient: the
+// product, over every factor, of (prefix scaled by the unit's dimensions,
+// times its conversion factor) raised to that factor's exponent. A
+// zero-point offset or an inverted unit has no well-defined meaning once
+// it's multiplied or divided against another unit, so compounding is
+// restricted to pure ratio units.
+fn factor_coefficient(factor: &ResolvedFactor, unit: &Unit) -> Result<f64, ConversionError>
+{
+    let &(prefix, _, exponent) = factor;
+
+    if unit.zero_point != 0.0 || unit.inverse
+    {
+        return Err(ConversionError::NonRatioUnit);
+    }
+
+    Ok((prefix_as_num(prefix).unwrap().powi(unit.dimensions as i32) * unit.conv_factor).powi(exponent))
+}
+
+fn convert_compound(input: f64, from_factors: &Vec<ResolvedFactor>, from_units: &Vec<Option<Rc<Unit>>>,
+    to_factors: &Vec<ResolvedFactor>, to_units: &Vec<Option<Rc<Unit>>>) -> Result<f64, ConversionError>
+{
+    let mut ratio = 1.0f64;
+
+    for (factor, unit) in from_factors.iter().zip(from_units.iter())
+    {
+        ratio *= try!(factor_coefficient(factor, unit.as_ref().unwrap()));
+    }
+
+    for (factor, unit) in to_factors.iter().zip(to_units.iter())
+    {
+        ratio /= try!(factor_coefficient(factor, unit.as_ref().unwrap()));
+    }
+
+    Ok(input * ratio)
+}
 
-/* Performs a unit conversion given as an input value, input unit and prefix,
- * and an output unit and prefix. Fetches the units from the given units database
- * A struct conversion is returned allowing the caller to do with it as they
- * please. Note that struct Conversion implements the Display trait and tracks
- * its own validity / error state. This function returns as soon as an error is
+/* Performs a unit conversion given as an input value and a from/to unit
+ * expression, each possibly a compound of several prefixed factors (ex.
+ * 'kg*m/s^2'). Fetches the units from the given units database. A struct
+ * Conversion is returned allowing the caller to do with it as they please.
+ * Note that struct Conversion implements the Display trait and tracks its
+ * own validity / error state. This function returns as soon as an error is
  * encountered.
  *
  * Parameters:
  *   - input: the value to be converted
- *   - from_prefix: the single character metric prefix of the input unit
- *   - from: name / alias of the unit to that will be converted
- *   - to_prefix: the single character metric prefix of the output unit
- *   - to: name / alias of the unit to convert to
+ *   - from: the input expression's factors, as (prefix, alias, exponent)
+ *   - to: the output expression's factors, as (prefix, alias, exponent)
  *   - units: reference to the database that holds all of the units
  *
- * Stages of Conversion:
- *   1. scale input using prefix and dimensions
- *   2. invert result if necessary
- *   3. change result to base units
- *   4. adjust result to output scale
- *   5. change result to output units
- *   6. invert result if necessary
- *   7. scale result using prefix and dimensions
+ * The single-factor, exponent-1 case (by far the most common) is run
+ * through the original 7-stage scalar algorithm unchanged. Anything more
+ * than that is resolved as a plain ratio: every factor's conversion
+ * coefficient is multiplied (numerator) or divided (denominator) together,
+ * which requires every factor to be a pure ratio unit (see
+ * 'factor_coefficient').
  */
-pub fn convert(input: f64, from_prefix: char, from: String,
-    to_prefix: char, to: String, units: &UnitDatabase) -> Conversion
+pub fn convert(input: f64, from: Vec<ResolvedFactor>,
+    to: Vec<ResolvedFactor>, units: &UnitDatabase) -> Conversion
 {
-    let mut conversion = Conversion::new(from_prefix, from, to_prefix, to, input);
+    let mut conversion = Conversion::new(from, to, input);
 
     // if the input value is NaN, INF, or too small
     // Exactly 0 is acceptable however which is_normal() does not account for
@@ -925,14 +1361,16 @@ pub fn convert(input: f64, from_prefix: char, from: String,
         return conversion;
     }
 
-    conversion.from = units.query(&conversion.from_alias);
-    conversion.to = units.query(&conversion.to_alias);
+    conversion.from = conversion.from_factors.iter()
+        .map(|&(_, ref alias, _)| units.query(alias)).collect();
+    conversion.to = conversion.to_factors.iter()
+        .map(|&(_, ref alias, _)| units.query(alias)).collect();
 
-    if conversion.from.is_none()
+    if conversion.from.iter().any(Option::is_none)
     {
         conversion.result = Err(ConversionError::UnitNotFound(INPUT));
     }
-    if conversion.to.is_none()
+    if conversion.to.iter().any(Option::is_none)
     {
         conversion.result = Err(ConversionError::UnitNotFound(OUTPUT));
     }
@@ -940,62 +1378,34 @@ pub fn convert(input: f64, from_prefix: char, from: String,
     {
         return conversion;
     }
-    
-    if conversion.to.as_ref().unwrap().unit_type !=
-        conversion.from.as_ref().unwrap().unit_type
+
+    if dimension_signature(&conversion.from, &conversion.from_factors) !=
+        dimension_signature(&conversion.to, &conversion.to_factors)
     {
         conversion.result = Err(ConversionError::TypeMismatch);
         return conversion;
     }
 
-    // do not initialize yet. we will fetch these values from conversion
-    let from_conv_factor: f64;
-    let from_zero_point: f64;
-    let from_dims: i32;
-    let from_is_inverse: bool;
-    let to_conv_factor: f64;
-    let to_zero_point: f64;
-    let to_dims: i32;
-    let to_is_inverse: bool;
-    {
-        // borrow scope for retrieving the unit properties
-        // avoids massive method chains on struct Conversion
-        let unit_from = conversion.from.as_ref().unwrap();
-        from_conv_factor = unit_from.conv_factor;
-        from_zero_point = unit_from.zero_point;
-        from_dims = unit_from.dimensions as i32;
-        from_is_inverse = unit_from.inverse;
-
-        let unit_to = conversion.to.as_ref().unwrap();
-        to_conv_factor = unit_to.conv_factor;
-        to_zero_point = unit_to.zero_point;
-        to_dims = unit_to.dimensions as i32;
-        to_is_inverse = unit_to.inverse;
-    } // end borrow scope
-
-    // S1
-    let mut output_val = conversion.input * prefix_as_num(
-        conversion.from_prefix)
-        .unwrap().powi(from_dims);
+    let is_simple = conversion.from_factors.len() == 1 && conversion.from_factors[0].2 == 1 &&
+                    conversion.to_factors.len() == 1 && conversion.to_factors[0].2 == 1;
 
-    // S2
-    if from_is_inverse
+    let output_val = if is_simple
     {
-        output_val = 1.0 / output_val;
+        convert_simple(conversion.input, &conversion.from_factors[0], conversion.from[0].as_ref().unwrap(),
+            &conversion.to_factors[0], conversion.to[0].as_ref().unwrap())
     }
-
-    output_val *= from_conv_factor; // S3
-    output_val += from_zero_point - to_zero_point; // S4
-    output_val /= to_conv_factor; // S5
-
-    // S6
-    if to_is_inverse
+    else
     {
-        output_val = 1.0 / output_val;
-    }
-
-    // S7
-    output_val /= prefix_as_num(conversion.to_prefix).unwrap().powi(to_dims);
+        match convert_compound(conversion.input, &conversion.from_factors, &conversion.from,
+            &conversion.to_factors, &conversion.to)
+        {
+            Ok(val) => val,
+            Err(err) => {
+                conversion.result = Err(err);
+                return conversion;
+            },
+        }
+    };
 
     // if the output value is NaN, INF, or too small to properly represent
     // Exactly 0 is acceptable however which is_normal() does not account for
@@ -1010,22 +1420,27 @@ pub fn convert(input: f64, from_prefix: char, from: String,
     conversion
 }
 
+fn to_resolved_factors(factors: &Vec<(UnitExpr, i32)>) -> Vec<ResolvedFactor>
+{
+    factors.iter()
+        .map(|&(ref unit_expr, exponent)| (unit_expr.prefix, unit_expr.alias.clone().unwrap(), exponent))
+        .collect()
+}
+
 pub fn convert_all(conv_primitive: ConvPrimitive, units: &UnitDatabase) -> Vec<Conversion>
 {
     let mut all_conversions = Vec::with_capacity(1);
+    let from_factors = to_resolved_factors(&conv_primitive.input_unit);
 
     for value_expr in conv_primitive.input_vals
     {
         for output_unit in conv_primitive.output_units.iter()
         {
             all_conversions.push(
-                convert(value_expr.value,
-                        conv_primitive.input_unit.prefix, conv_primitive.input_unit.alias.clone().unwrap(),
-                        output_unit.clone().prefix, output_unit.clone().alias.unwrap(),
-                        units)
+                convert(value_expr.value, from_factors.clone(), to_resolved_factors(output_unit), units)
             );
         }
     }
-    
+
     all_conversions
 }
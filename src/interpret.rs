@@ -34,6 +34,7 @@ use std::fmt;
 use std::fmt::Display;
 use std::fmt::Write;
 use std::error::Error;
+use std::ops::Range;
 
 static NONLITERAL_RECALL_MSG: &'static str = "recall variables must be literals";
 
@@ -148,7 +149,7 @@ impl LineCheck
 
 impl SyntaxChecker for LineCheck
 {
-	fn feed_token(&mut self, token: &str, delim: bool, index: usize) -> bool
+	fn feed_token(&mut self, token: &str, delim: bool, range: Range<usize>) -> bool
 	{
 		if !delim && !token.is_empty()
 		{
@@ -183,7 +184,7 @@ impl SyntaxChecker for LineCheck
 	{
 		self.valid
 	}
-	fn assert_valid(&self, index: usize, more_tokens: bool) -> Result<(), SyntaxError>
+	fn assert_valid(&self, range: Range<usize>, more_tokens: bool) -> Result<(), SyntaxError>
 	{
 		Ok(())
 	}
@@ -199,6 +200,12 @@ impl SyntaxChecker for LineCheck
 	{
 		self.esc = false;
 	}
+	fn report_and_continue(&mut self, _err: SyntaxError) -> bool
+	{
+		// line dispatch doesn't support error recovery, just poison as usual
+		self.valid = false;
+		self.valid
+	}
 }
 
 pub struct Interpreter<I, O> where I: Read, O:io:: Write
@@ -207,6 +214,7 @@ pub struct Interpreter<I, O> where I: Read, O:io:: Write
 	input_stream: BufReader<I>,
 	output_stream: O,
 	input_value: Option<f64>,
+	value_history: Vec<f64>, // every successfully converted/entered input value, oldest first
 	input_unit: Option<String>,
 	output_unit: Option<String>,
 }
@@ -219,10 +227,19 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 		              input_stream: BufReader::new(istream),
 		              output_stream: ostream,
 		              input_value: None,
+		              value_history: Vec::new(),
 		              input_unit: None,
 		              output_unit: None,
 		}
 	}
+
+	// Records a value as the most recent input value and appends it to the
+	// session's recall history.
+	fn record_value(&mut self, value: f64)
+	{
+		self.input_value = Some(value);
+		self.value_history.push(value);
+	}
 	
 	/* Gets the next line from the input stream and interpets as either a
 	 * conversion or a command. If it is a command ie beginning in a program
@@ -288,6 +305,13 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 				"s" => ConversionFmt::Short,
 				"d" => ConversionFmt::Desc,
 				"l" => ConversionFmt::Long,
+				fmt if fmt.starts_with('g') => {
+					match fmt[1..].parse::<u8>()
+					{
+					Ok(figs) if figs > 0 => ConversionFmt::Sig(figs),
+					_ => return Err(InterpretErr::InvalidState(value.peek().clone())),
+					}
+				},
 				_ => return Err(InterpretErr::InvalidState(value.peek().clone())),
 				};
 				
@@ -365,7 +389,7 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 			else
 			{
 				let value_expr_result = parse_number_expr(next_tok.unwrap().peek());
-				
+
 				if value_expr_result.is_err()
 				{
 					let mut err_str = String::with_capacity(80);
@@ -375,13 +399,13 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 				
 				let value_expr = value_expr_result.unwrap();
 				
-				if value_expr.recall == true
+				if value_expr.recall != Recall::None
 				{
 					return Err(InterpretErr::InvalidState(
 							NONLITERAL_RECALL_MSG.to_string()));
 				}
-				
-				self.input_value = Some(value_expr.value);
+
+				self.record_value(value_expr.value);
 				cmd_result = InterpretErr::CmdSuccess("Okay.".to_string());
 			}
 		},
@@ -415,44 +439,88 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 		Ok(tokens)
 	}
 	
-	pub fn perform_recall(&self, exprs: &mut ConvPrimitive) -> Option<InterpretErr>
+	// Resolves the Nth-previous result counting back from the most recent
+	// (1 == most recent), or the 1-based absolute index into the session's
+	// recall history, whichever 'recall' calls for.
+	fn resolve_recall(&self, recall: Recall) -> Option<f64>
 	{
-		if exprs.input_val.recall
+		match recall
 		{
-			exprs.input_val.value = match self.input_value
+		Recall::None => None,
+		Recall::Last => self.value_history.last().cloned(),
+		Recall::Back(n) => {
+			self.value_history.len()
+				.checked_sub(n as usize)
+				.and_then(|i| self.value_history.get(i))
+				.cloned()
+		},
+		Recall::Index(n) => {
+			if n < 1
 			{
-				None => {
-					return Some(InterpretErr::RecallErr("input value".to_string(),
-					                                   "not set". to_string()));
-				},
-				Some(val) => val,
+				return None;
 			}
+			self.value_history.get((n - 1) as usize).cloned()
+		},
 		}
-		
-		if exprs.input_unit.recall
+	}
+
+	// Substitutes the stored alias (from a prior conversion's 'from_expr'/
+	// 'to_expr') into every factor of a compound unit expression that asked
+	// for recall (':'), leaving every other factor untouched. A compound
+	// expression can carry at most one kind of recall per side, but several
+	// factors may each individually ask for it (ex. ':*:'), so every factor
+	// is checked rather than just the first.
+	fn resolve_unit_recall(&self, factors: &mut Vec<(UnitExpr, i32)>, stored: &Option<String>,
+		which: &str) -> Option<InterpretErr>
+	{
+		for &mut (ref mut unit_expr, _) in factors.iter_mut()
 		{
-			exprs.input_unit.alias = match self.input_unit
+			if unit_expr.recall
 			{
-				None => {
-					return Some(InterpretErr::RecallErr("input unit".to_string(),
-					                                   "not set". to_string()));
+				unit_expr.alias = match *stored
+				{
+					None => {
+						return Some(InterpretErr::RecallErr(which.to_string(),
+						                                   "not set". to_string()));
+					}
+					Some(ref alias) => Some(alias.clone()),
 				}
-				Some(ref alias) => Some(alias.clone()),
 			}
 		}
-		
-		if exprs.output_unit.recall
+
+		None
+	}
+
+	pub fn perform_recall(&self, exprs: &mut ConvPrimitive) -> Option<InterpretErr>
+	{
+		for value_expr in exprs.input_vals.iter_mut()
 		{
-			exprs.output_unit.alias = match self.output_unit
+			if value_expr.recall != Recall::None
 			{
-				None => {
-					return Some(InterpretErr::RecallErr("output unit".to_string(),
-					                                   "not set". to_string()));
+				value_expr.value = match self.resolve_recall(value_expr.recall)
+				{
+					None => {
+						return Some(InterpretErr::RecallErr("input value".to_string(),
+						                                   "not set". to_string()));
+					},
+					Some(val) => val,
 				}
-				Some(ref alias) => Some(alias.clone()),
 			}
 		}
-		
+
+		if let Some(err) = self.resolve_unit_recall(&mut exprs.input_unit, &self.input_unit, "input unit")
+		{
+			return Some(err);
+		}
+
+		for output_unit in exprs.output_units.iter_mut()
+		{
+			if let Some(err) = self.resolve_unit_recall(output_unit, &self.output_unit, "output unit")
+			{
+				return Some(err);
+			}
+		}
+
 		None
 	}
 	
@@ -468,33 +536,33 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 				ConversionError::OutOfRange(output) => {
 					if output
 					{
-						self.input_value = Some(conversion.input);
+						self.record_value(conversion.input);
 					}
-					self.input_unit = Some(conversion.from_alias.clone());
-					self.output_unit = Some(conversion.to_alias.clone());
+					self.input_unit = Some(conversion.from_expr());
+					self.output_unit = Some(conversion.to_expr());
 				},
-				ConversionError::TypeMismatch => {
-					self.input_value = Some(conversion.input);
-					self.input_unit = Some(conversion.from_alias.clone());
-					self.output_unit = Some(conversion.to_alias.clone());
+				ConversionError::TypeMismatch | ConversionError::NonRatioUnit => {
+					self.record_value(conversion.input);
+					self.input_unit = Some(conversion.from_expr());
+					self.output_unit = Some(conversion.to_expr());
 				},
 				ConversionError::UnitNotFound(..) => {
-					if conversion.to.is_some()
+					if conversion.to.iter().all(Option::is_some)
 					{
-						self.output_unit = Some(conversion.to_alias.clone());
+						self.output_unit = Some(conversion.to_expr());
 					}
-					if conversion.from.is_some()
+					if conversion.from.iter().all(Option::is_some)
 					{
-						self.input_unit = Some(conversion.from_alias.clone());
+						self.input_unit = Some(conversion.from_expr());
 					}
-					self.input_value = Some(conversion.input);
+					self.record_value(conversion.input);
 				},
 				};
 			},
 			_ => {
-				self.input_value = Some(conversion.input);
-				self.input_unit = Some(conversion.from_alias.clone());
-				self.output_unit = Some(conversion.to_alias.clone());
+				self.record_value(conversion.input);
+				self.input_unit = Some(conversion.from_expr());
+				self.output_unit = Some(conversion.to_expr());
 			},
 			};
 		}
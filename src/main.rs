@@ -10,6 +10,7 @@ extern crate serde_yaml;
 extern crate log4rs;
 
 mod units;
+mod quantity;
 
 use units::UnitDatabase;
 use structopt::StructOpt;
@@ -75,9 +76,9 @@ fn main() {
         _ => (),
     }
     let units_db = match UnitDatabase::load_from_file("../../cfg/units.yaml".to_string(), None) {
-        Some(db) => db,
-        None => {
-            error!("Failed to load units. Exiting");
+        Ok(db) => db,
+        Err(err) => {
+            error!("Failed to load units: {}", err);
             return;
         }
     };
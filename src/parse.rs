@@ -3,12 +3,59 @@ use std::error::Error;
 use std::fmt;
 use std::fmt::Formatter;
 use std::fmt::Display;
+use std::ops::Range;
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum SyntaxError
 {
-	Expected(usize, String),
-	BadEscSeq(usize, char),
+	Expected(Range<usize>, String),
+	BadEscSeq(Range<usize>, char),
+}
+
+impl SyntaxError
+{
+	// Returns the human readable message carried by this error, without the
+	// "syntax error @ col N" framing that Display adds.
+	pub fn message(&self) -> String
+	{
+		match *self
+		{
+		SyntaxError::Expected(_, ref msg) => format!("expected {}", msg),
+		SyntaxError::BadEscSeq(_, ref ch) => format!("bad escape sequence: \\{}", ch),
+		}
+	}
+
+	// Returns the character range of the offending span in the original input line.
+	pub fn range(&self) -> Range<usize>
+	{
+		match *self
+		{
+		SyntaxError::Expected(ref range, _) => range.clone(),
+		SyntaxError::BadEscSeq(ref range, _) => range.clone(),
+		}
+	}
+
+	// Renders the original line with the offending span underlined by carets,
+	// the way rustc / rust-analyzer point at a `TextRange` beneath a line of
+	// source. Meant for CLI presentation of parse failures.
+	pub fn render_carets(&self, line: &str) -> String
+	{
+		let range = self.range();
+		let start = range.start.min(line.chars().count());
+		let end = range.end.max(start + 1);
+		let mut carets = String::with_capacity(end);
+
+		for _ in 0..start
+		{
+			carets.push(' ');
+		}
+		for _ in start..end
+		{
+			carets.push('^');
+		}
+
+		format!("{}\n{}", line, carets)
+	}
 }
 
 impl Error for SyntaxError
@@ -21,7 +68,7 @@ impl Error for SyntaxError
 		SyntaxError::BadEscSeq(..) => "reached bad escape sequence",
 		}
 	}
-	
+
 	fn cause(&self) -> Option<&Error>
 	{
 		None
@@ -34,11 +81,11 @@ impl Display for SyntaxError
 	{
 		match *self
 		{
-		SyntaxError::Expected(ref index, ref msg) => {
-			write!(f, "syntax error @ col {}: expected {}", index+1, msg)
+		SyntaxError::Expected(ref range, ref msg) => {
+			write!(f, "syntax error @ col {}: expected {}", range.start+1, msg)
 		},
-		SyntaxError::BadEscSeq(ref index, ref ch) => {
-			write!(f, "syntax error @ col {}: bad escape sequence: \\{}", index+1, ch)
+		SyntaxError::BadEscSeq(ref range, ref ch) => {
+			write!(f, "syntax error @ col {}: bad escape sequence: \\{}", range.start+1, ch)
 		},
 		}
 	}
@@ -52,17 +99,17 @@ impl Display for SyntaxError
  *   'fn tokenize' for more details.
  *
  * Usage:
- *   fn feed_token(&mut self, &str, usize) -> bool
+ *   fn feed_token(&mut self, &str, bool, Range<usize>) -> bool
  *     Checks the next token against syntax rules. Returns true if the syntax
  *     check encountered no errors. Returns false otherwise.
  *
- *     - &mut self  : mutable reference to the struct implementing this trait.
- *                    mutation may not be required depending on implementation,
- *                    it is best to have the option.
- *     - &str       : token to check
- *     - bool       : indicates whether a delimiter or token was fed. true for
- *                    delimiter, false for token.
- *     - usize      : index where tokenization left off
+ *     - &mut self     : mutable reference to the struct implementing this trait.
+ *                       mutation may not be required depending on implementation,
+ *                       it is best to have the option.
+ *     - &str          : token to check
+ *     - bool          : indicates whether a delimiter or token was fed. true for
+ *                       delimiter, false for token.
+ *     - Range<usize>  : character range of the token within the line being tokenized
  *
  *   fn is_esc(&self, char) -> bool
  *     Checks if the given char was this syntax's escape sequence char. Returns
@@ -86,15 +133,15 @@ impl Display for SyntaxError
  *     Returns the state of the syntax checker. True if no rules have been
  *     violated, false otherwise.
  *
- *   fn asser_valid(&self, usize, bool) -> Result<(), SyntaxError>
+ *   fn asser_valid(&self, Range<usize>, bool) -> Result<(), SyntaxError>
  *     Asserts that the syntax is valid by returning nothing if it is and a
  *     SyntaxError if it is invalid. Meant for ergonomic use with try! macro.
  *     Configurable to check whether the syntax is in a valid exit state where
  *     no more tokens will be received or whether the syntax is in valid
  *     progressive state where we are expecting more tokens.
  *
- *     - usize      : index where tokenization left off
- *     - bool       : indicates whether we are expecting more tokens or not
+ *     - Range<usize> : character range of the token where tokenization left off
+ *     - bool         : indicates whether we are expecting more tokens or not
  *                    True for expecting more, False for no more tokens.
  *
  *   fn esc_set(&self) -> bool
@@ -109,24 +156,73 @@ impl Display for SyntaxError
  *
  *   fn reset(&mut self)
  *     Resets this syntax to its default state.
+ *
+ *   fn report_and_continue(&mut self, SyntaxError) -> bool
+ *     Error-recovery hook, mirroring rustc's SemiColonMode::{Break, Ignore}.
+ *     Implementors that support recovery should record the given error
+ *     rather than raising it immediately and resynchronize their state so
+ *     tokenization can keep running, letting a single call to 'tokenize'
+ *     surface every problem instead of only the first one. Implementors
+ *     that don't support recovery may simply poison themselves as
+ *     'feed_token' already does. Returns the same thing 'valid' would.
+ *
+ *     - SyntaxError : the error that would otherwise have been raised
  */
 pub trait SyntaxChecker
 {
-	fn feed_token(&mut self, token: &str, delim: bool, index: usize) -> bool;
+	fn feed_token(&mut self, token: &str, delim: bool, range: Range<usize>) -> bool;
 	fn is_esc(&self, ch: char) -> bool;
 	fn is_comment(&self, ch: char) -> bool;
 	fn is_delim(&self, ch: char) -> bool;
 	fn is_preserved_delim(&self, ch: char) -> bool;
 	fn esc_char(&self) -> char;
 	fn valid(&self) -> bool;
-	fn assert_valid(&self, index: usize, more_tokens: bool) -> Result<(), SyntaxError>;
+	fn assert_valid(&self, range: Range<usize>, more_tokens: bool) -> Result<(), SyntaxError>;
 	fn esc_set(&self) -> bool;
 	fn set_esc(&mut self, set: bool);
 	fn reset(&mut self);
+	fn report_and_continue(&mut self, err: SyntaxError) -> bool;
 }
 
 const DELIM: bool = true; // constant for indicated delimiter to SyntaxChecker trait
 
+/* struct Position
+ *
+ * Description: a character offset into the original input line together with a
+ *   span length, the way a script parser reports (line, column) but for a
+ *   single line of input. Carried on every TokenType so callers downstream
+ *   of 'tokenize' can point a caret at the exact characters that produced
+ *   an error rather than just the token's index.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position
+{
+	pub offset: usize,
+	pub len: usize,
+}
+
+impl Position
+{
+	pub fn new(offset: usize, len: usize) -> Position
+	{
+		Position { offset: offset, len: len }
+	}
+
+	// Recovers the character range this position covers in the original line.
+	pub fn range(&self) -> Range<usize>
+	{
+		self.offset..(self.offset + self.len)
+	}
+}
+
+impl From<Range<usize>> for Position
+{
+	fn from(range: Range<usize>) -> Position
+	{
+		Position { offset: range.start, len: range.end.saturating_sub(range.start) }
+	}
+}
+
 /* enum TokenType
  *
  * Description: wrapper for tokens that denotes them as either delimiters or
@@ -138,14 +234,14 @@ const DELIM: bool = true; // constant for indicated delimiter to SyntaxChecker t
  *     to be discarded.
  *
  * Contained Types:
- *   - Delim(String)  : wraps a string that is a delimiter
- *   - Normal(String) : wraps a string that is a Normal
+ *   - Delim(String, Position)  : wraps a string that is a delimiter
+ *   - Normal(String, Position) : wraps a string that is a Normal
  */
 #[derive(Debug)]
 pub enum TokenType
 {
-	Delim (String),
-	Normal(String),
+	Delim (String, Position),
+	Normal(String, Position),
 }
 
 impl TokenType
@@ -156,8 +252,8 @@ impl TokenType
 	{
 		match self
 		{
-			TokenType::Delim(tok)  => return tok,
-			TokenType::Normal(tok) => return tok,
+			TokenType::Delim(tok, _)  => return tok,
+			TokenType::Normal(tok, _) => return tok,
 		}
 	}
 	
@@ -167,8 +263,8 @@ impl TokenType
 	{
 		match *self
 		{
-		TokenType::Delim(ref tok) => tok,
-		TokenType::Normal(ref tok) => tok,
+		TokenType::Delim(ref tok, _) => tok,
+		TokenType::Normal(ref tok, _) => tok,
 		}
 	}
 	
@@ -178,8 +274,18 @@ impl TokenType
 	{
 		match *self
 		{
-			TokenType::Delim(ref tok)  => return tok.is_empty(),
-			TokenType::Normal(ref tok) => return tok.is_empty(),
+			TokenType::Delim(ref tok, _)  => return tok.is_empty(),
+			TokenType::Normal(ref tok, _) => return tok.is_empty(),
+		}
+	}
+
+	// Returns the character span this token occupied in the original input line.
+	pub fn position(&self) -> Position
+	{
+		match *self
+		{
+			TokenType::Delim(_, pos)  => pos,
+			TokenType::Normal(_, pos) => pos,
 		}
 	}
 }
@@ -206,7 +312,7 @@ pub fn tokenize<S: SyntaxChecker>(line: &str, checker: &mut S) -> Result<Vec<Tok
 	if line.is_empty()
 	{
 		let mut tokens = Vec::with_capacity(1);
-		tokens.push(TokenType::Normal(String::new()));
+		tokens.push(TokenType::Normal(String::new(), Position::new(0, 0)));
 		return Ok(tokens);
 	}
 	let mut buffer = String::with_capacity(line.len()); // biggest token is possible is the line unmodified
@@ -214,6 +320,7 @@ pub fn tokenize<S: SyntaxChecker>(line: &str, checker: &mut S) -> Result<Vec<Tok
 	let mut delim_pushed = false;
 	let mut last: usize = 0;
 	let mut last_ch: char = '\0';
+	let mut token_start: usize = 0; // index where the token currently in 'buffer' began
 
 	for (index, ch) in line.chars().enumerate()
 	{
@@ -247,20 +354,21 @@ pub fn tokenize<S: SyntaxChecker>(line: &str, checker: &mut S) -> Result<Vec<Tok
 		{
 			let mut new_token = buffer.clone();
 			new_token.shrink_to_fit();
-			checker.feed_token(&new_token, !DELIM, index);
+			checker.feed_token(&new_token, !DELIM, token_start..index);
 
-			tokens.push(TokenType::Normal(new_token));
+			tokens.push(TokenType::Normal(new_token, Position::from(token_start..index)));
 
 			buffer.clear();
 			buffer.push(ch);
 
 			new_token = buffer.clone();
 			new_token.shrink_to_fit();
-			checker.feed_token(&new_token, DELIM, index);
+			checker.feed_token(&new_token, DELIM, index..(index+1));
 
-			tokens.push(TokenType::Delim(new_token));
+			tokens.push(TokenType::Delim(new_token, Position::from(index..(index+1))));
 
 			buffer.clear();
+			token_start = index + 1;
 			delim_pushed = true;
 		}
 		else if checker.is_comment(ch)
@@ -268,10 +376,10 @@ pub fn tokenize<S: SyntaxChecker>(line: &str, checker: &mut S) -> Result<Vec<Tok
 			let mut new_token = buffer.clone();
 			new_token.shrink_to_fit();
 
-			checker.feed_token(&new_token, !DELIM, index);
+			checker.feed_token(&new_token, !DELIM, token_start..index);
 
-			tokens.push(TokenType::Normal(new_token));
-			try!(checker.assert_valid(index, true));
+			tokens.push(TokenType::Normal(new_token, Position::from(token_start..index)));
+			try!(checker.assert_valid(index..(index+1), true));
 			return Ok(tokens); // if we reach a comment, immediately exit
 		}
 		else
@@ -280,14 +388,14 @@ pub fn tokenize<S: SyntaxChecker>(line: &str, checker: &mut S) -> Result<Vec<Tok
 			delim_pushed = false;
 		}
 
-		try!(checker.assert_valid(index, true));
+		try!(checker.assert_valid(index..(index+1), true));
 		last = index;
 		last_ch = ch;
 	}
 
 	if checker.esc_set()
 	{
-		return Err(SyntaxError::BadEscSeq(last,
+		return Err(SyntaxError::BadEscSeq(last..(last+1),
 						if last_ch == checker.esc_char()
 						{
 							'\0'
@@ -305,16 +413,198 @@ pub fn tokenize<S: SyntaxChecker>(line: &str, checker: &mut S) -> Result<Vec<Tok
 	{
 		new_token = buffer.clone();
 		new_token.shrink_to_fit();
-		checker.feed_token(&new_token, !DELIM, last);
-		tokens.push(TokenType::Normal(new_token));
+		checker.feed_token(&new_token, !DELIM, token_start..(last+1));
+		tokens.push(TokenType::Normal(new_token, Position::from(token_start..(last+1))));
 	}
 	else if delim_pushed
 	{
-		checker.feed_token(&new_token, !DELIM, last);
-		tokens.push(TokenType::Normal(new_token));
+		checker.feed_token(&new_token, !DELIM, token_start..token_start);
+		tokens.push(TokenType::Normal(new_token, Position::from(token_start..token_start)));
 	}
 
-	try!(checker.assert_valid(last, false));
+	try!(checker.assert_valid(last..(last+1), false));
 
 	Ok(tokens)
 }
+
+/* Collect-all-errors variant of 'tokenize'. Behaves identically to
+ * 'tokenize' for a syntactically valid line. When a 'SyntaxError' would
+ * abort 'tokenize', this instead records the error, skips ahead to the next
+ * delimiter (the recovery point 'delim_pushed' above already tracks, since
+ * a delimiter always starts a fresh token), resets 'checker' via 'reset()',
+ * and keeps tokenizing from there - so one call reports every problem on
+ * the line instead of just the first. A trailing comment still ends
+ * tokenizing outright, same as 'tokenize', since everything after it is
+ * discarded anyway. Prefer 'tokenize' when a caller only needs to know
+ * whether a line is valid; use this when presenting every diagnostic on a
+ * malformed line at once (eg. units.cfg syntax errors).
+ *
+ * Parameters:
+ *   - line    : string of text to be tokenized
+ *   - checker : set of syntax rules to tokenize with. must implement
+ *               SyntaxChecker trait
+ */
+pub fn tokenize_all<S: SyntaxChecker>(line: &str, checker: &mut S) -> (Vec<TokenType>, Vec<SyntaxError>)
+{
+	if line.is_empty()
+	{
+		let mut tokens = Vec::with_capacity(1);
+		tokens.push(TokenType::Normal(String::new(), Position::new(0, 0)));
+		return (tokens, Vec::new());
+	}
+
+	let chars: Vec<char> = line.chars().collect();
+	let mut buffer = String::with_capacity(line.len());
+	let mut tokens = Vec::with_capacity(5);
+	let mut errs: Vec<SyntaxError> = Vec::new();
+	let mut delim_pushed = false;
+	let mut last: usize = 0;
+	let mut last_ch: char = '\0';
+	let mut token_start: usize = 0;
+	let mut index = 0;
+
+	while index < chars.len()
+	{
+		let ch = chars[index];
+
+		if checker.is_esc(ch) && !checker.esc_set()
+		{
+			checker.set_esc(true);
+		}
+		else if checker.esc_set()
+		{
+			if checker.is_delim(ch) || checker.is_esc(ch) || checker.is_comment(ch)
+			{
+				buffer.push(ch);
+				checker.set_esc(false);
+				delim_pushed = false;
+			}
+			else if checker.is_preserved_delim(ch)
+			{
+				buffer.push(checker.esc_char());
+				buffer.push(ch);
+				checker.set_esc(false);
+				delim_pushed = false;
+			}
+			else
+			{
+				errs.push(SyntaxError::BadEscSeq(index..(index+1), ch));
+
+				let mut resync_at = index;
+				while resync_at < chars.len() && !checker.is_delim(chars[resync_at])
+				{
+					resync_at += 1;
+				}
+
+				buffer.clear();
+				checker.reset();
+				delim_pushed = false;
+				token_start = resync_at;
+				index = resync_at;
+				continue;
+			}
+		}
+		else if checker.is_delim(ch)
+		{
+			let mut new_token = buffer.clone();
+			new_token.shrink_to_fit();
+			checker.feed_token(&new_token, !DELIM, token_start..index);
+
+			tokens.push(TokenType::Normal(new_token, Position::from(token_start..index)));
+
+			buffer.clear();
+			buffer.push(ch);
+
+			new_token = buffer.clone();
+			new_token.shrink_to_fit();
+			checker.feed_token(&new_token, DELIM, index..(index+1));
+
+			tokens.push(TokenType::Delim(new_token, Position::from(index..(index+1))));
+
+			buffer.clear();
+			token_start = index + 1;
+			delim_pushed = true;
+		}
+		else if checker.is_comment(ch)
+		{
+			let mut new_token = buffer.clone();
+			new_token.shrink_to_fit();
+
+			checker.feed_token(&new_token, !DELIM, token_start..index);
+
+			tokens.push(TokenType::Normal(new_token, Position::from(token_start..index)));
+
+			if let Err(err) = checker.assert_valid(index..(index+1), true)
+			{
+				errs.push(err);
+			}
+
+			return (tokens, errs); // comments discard the rest of the line, same as 'tokenize'
+		}
+		else
+		{
+			buffer.push(ch);
+			delim_pushed = false;
+		}
+
+		if let Err(err) = checker.assert_valid(index..(index+1), true)
+		{
+			errs.push(err);
+
+			let mut resync_at = index + 1;
+			while resync_at < chars.len() && !checker.is_delim(chars[resync_at])
+			{
+				resync_at += 1;
+			}
+
+			buffer.clear();
+			checker.reset();
+			delim_pushed = false;
+			token_start = resync_at;
+			index = resync_at;
+			continue;
+		}
+
+		last = index;
+		last_ch = ch;
+		index += 1;
+	}
+
+	if checker.esc_set()
+	{
+		errs.push(SyntaxError::BadEscSeq(last..(last+1),
+						if last_ch == checker.esc_char()
+						{
+							'\0'
+						}
+						else
+						{
+							last_ch
+						})
+		);
+		checker.reset();
+		return (tokens, errs);
+	}
+
+	let mut new_token = String::new();
+
+	if !buffer.is_empty()
+	{
+		new_token = buffer.clone();
+		new_token.shrink_to_fit();
+		checker.feed_token(&new_token, !DELIM, token_start..(last+1));
+		tokens.push(TokenType::Normal(new_token, Position::from(token_start..(last+1))));
+	}
+	else if delim_pushed
+	{
+		checker.feed_token(&new_token, !DELIM, token_start..token_start);
+		tokens.push(TokenType::Normal(new_token, Position::from(token_start..token_start)));
+	}
+
+	if let Err(err) = checker.assert_valid(last..(last+1), false)
+	{
+		errs.push(err);
+	}
+
+	(tokens, errs)
+}
@@ -0,0 +1,184 @@
+use std::{
+    fmt::{self, Display, Formatter},
+    rc::Rc,
+};
+
+use units::{Unit, UnitDatabase};
+
+/// SI prefixes `Quantity::parse` recognizes, looked up by exact match.
+/// `"da"` (deka) is the only two-character entry; `parse` peels it off
+/// before trying a single-character prefix. `'u'` is accepted
+/// as the ASCII-typeable alias for `'µ'` (micro), matching how they're used
+/// interchangeably in plain text.
+const SI_PREFIXES: [(&str, f64); 21] = [
+    ("da", 1.0e1),
+    ("Y", 1.0e24),
+    ("Z", 1.0e21),
+    ("E", 1.0e18),
+    ("P", 1.0e15),
+    ("T", 1.0e12),
+    ("G", 1.0e9),
+    ("M", 1.0e6),
+    ("k", 1.0e3),
+    ("h", 1.0e2),
+    ("d", 1.0e-1),
+    ("c", 1.0e-2),
+    ("m", 1.0e-3),
+    ("µ", 1.0e-6),
+    ("u", 1.0e-6),
+    ("n", 1.0e-9),
+    ("p", 1.0e-12),
+    ("f", 1.0e-15),
+    ("a", 1.0e-18),
+    ("z", 1.0e-21),
+    ("y", 1.0e-24),
+];
+
+fn si_prefix_value(prefix: &str) -> Option<f64> {
+    SI_PREFIXES.iter()
+        .find(|(text, _)| *text == prefix)
+        .map(|(_, value)| *value)
+}
+
+/// A value parsed out of a user string alongside the unit it was expressed
+/// in, ex. `"10.5 km"` -> `Quantity { value: 10.5, prefix: 1.0e3, unit: <km's
+/// meter entry>, tag: None }`. `value * prefix` is the scalar in `unit`'s own
+/// scale, the same quantity `units::Unit::convert` expects.
+#[derive(Debug)]
+pub struct Quantity {
+    pub value: f64,
+    pub prefix: f64,
+    pub unit: Rc<Unit>,
+    pub tag: Option<Rc<String>>,
+}
+
+#[derive(Debug)]
+pub enum QuantityParseError {
+    EmptyInput,
+    MalformedNumber(String),
+    UnknownUnit(String),
+    UnknownPrefix(String),
+}
+
+impl Display for QuantityParseError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            QuantityParseError::EmptyInput => write!(f, "no quantity was given"),
+            QuantityParseError::MalformedNumber(text) => write!(f, "'{}' is not a number", text),
+            QuantityParseError::UnknownUnit(text) => write!(f, "no unit called '{}' is registered", text),
+            QuantityParseError::UnknownPrefix(text) => write!(f, "'{}' is not a recognized SI prefix", text),
+        }
+    }
+}
+
+impl std::error::Error for QuantityParseError {}
+
+/// Length, in bytes, of the numeric literal `body` starts with. Digits and
+/// `.` are always numeric; a leading `+`/`-` is a sign. `e`/`E` is only
+/// numeric in exponent context - immediately preceded by a digit and
+/// immediately followed by a digit (optionally through a `+`/`-` exponent
+/// sign) - so a unit name starting with 'e' right after the number (ex.
+/// `"10erg"`, `"5eV"`) isn't swallowed into the literal.
+fn numeric_prefix_len(body: &str) -> usize {
+    let chars: Vec<char> = body.chars().collect();
+    let mut i = 0;
+
+    while i < chars.len() {
+        let ch = chars[i];
+
+        if ch.is_ascii_digit() || ch == '.' {
+            i += 1;
+        } else if ch == '+' || ch == '-' {
+            // a leading sign, or an exponent sign directly after 'e'/'E'
+            if i == 0 || chars[i - 1] == 'e' || chars[i - 1] == 'E' {
+                i += 1;
+            } else {
+                break;
+            }
+        } else if ch == 'e' || ch == 'E' {
+            let prev_is_digit = i > 0 && chars[i - 1].is_ascii_digit();
+            let next = if i + 1 < chars.len() && (chars[i + 1] == '+' || chars[i + 1] == '-') { i + 2 } else { i + 1 };
+            let next_is_digit = next < chars.len() && chars[next].is_ascii_digit();
+
+            if prev_is_digit && next_is_digit {
+                i += 1;
+            } else {
+                break;
+            }
+        } else {
+            break;
+        }
+    }
+
+    chars[..i].iter().map(|c| c.len_utf8()).sum()
+}
+
+impl Quantity {
+    /// Parses `input` (ex. `"10.5 km"`, `"3mV"`, `"2 L@metric"`) against
+    /// `units`, the database to resolve the unit token and its optional
+    /// `@tag` suffix through.
+    ///
+    /// The numeric literal is split from the unit token first, then the
+    /// unit token is resolved in three steps:
+    ///   1. tried bare, with no prefix, so a unit whose own name starts with
+    ///      a letter that also happens to be a prefix (ex. "mol") isn't
+    ///      misread as a prefix plus a nonexistent unit;
+    ///   2. failing that, its leading "da" (or else its first character) is
+    ///      peeled off as a prefix candidate and the remainder is looked up
+    ///      as the unit;
+    ///   3. a candidate that isn't a recognized SI prefix, or a remainder
+    ///      that isn't a registered unit, is reported back distinctly as
+    ///      `UnknownPrefix`/`UnknownUnit` so the caller can say which.
+    pub fn parse(input: &str, units: &UnitDatabase) -> Result<Quantity, QuantityParseError> {
+        let input = input.trim();
+
+        if input.is_empty() {
+            return Err(QuantityParseError::EmptyInput);
+        }
+
+        let (body, tag) = match input.find('@') {
+            Some(at) => (&input[..at], Some(Rc::new(input[at + 1..].to_string()))),
+            None => (input, None),
+        };
+        let body = body.trim();
+
+        let split_at = numeric_prefix_len(body);
+        let (number, unit_token) = body.split_at(split_at);
+        let unit_token = unit_token.trim();
+
+        let value: f64 = number.parse()
+            .map_err(|_| QuantityParseError::MalformedNumber(number.to_string()))?;
+
+        if let Some(unit) = units.query(&unit_token.to_string(), tag.as_ref().map(|t| t.as_ref())) {
+            return Ok(Quantity { value, prefix: 1.0, unit, tag });
+        }
+
+        let (candidate, remainder) = if unit_token.starts_with("da") {
+            unit_token.split_at(2)
+        } else {
+            match unit_token.chars().next() {
+                Some(first) => unit_token.split_at(first.len_utf8()),
+                None => return Err(QuantityParseError::UnknownUnit(unit_token.to_string())),
+            }
+        };
+
+        if remainder.is_empty() {
+            return Err(QuantityParseError::UnknownUnit(unit_token.to_string()));
+        }
+
+        match si_prefix_value(candidate) {
+            Some(prefix) => {
+                units.query(&remainder.to_string(), tag.as_ref().map(|t| t.as_ref()))
+                    .map(|unit| Quantity { value, prefix, unit, tag })
+                    .ok_or_else(|| QuantityParseError::UnknownUnit(remainder.to_string()))
+            },
+            None => {
+                if units.query(&remainder.to_string(), tag.as_ref().map(|t| t.as_ref())).is_some() {
+                    Err(QuantityParseError::UnknownPrefix(candidate.to_string()))
+                } else {
+                    Err(QuantityParseError::UnknownUnit(unit_token.to_string()))
+                }
+            },
+        }
+    }
+}
@@ -1,7 +1,9 @@
 
+use std::error::Error;
 use std::fmt;
 use std::fmt::{Display, Formatter};
 use std::rc::Rc;
+use std::str::FromStr;
 
 use ::runtime::units::{Unit, UnitDatabase};
 use ::runtime::parse::ConvPrimitive;
@@ -18,6 +20,9 @@ pub enum ConversionError
 const INPUT: bool = false;
 const OUTPUT: bool = true;
 
+// how many corrections a 'ConversionError::UnitNotFound' offers at most
+const SUGGESTION_LIMIT: usize = 3;
+
 
 #[derive(Debug, Copy, Clone)]
 pub enum ConversionFmt
@@ -25,6 +30,10 @@ pub enum ConversionFmt
     Short,
     Desc,
     Long,
+    // one CSV record per conversion: input_value,from_unit,to_unit,result,error
+    // with exactly one of 'result'/'error' populated. Meant for piping a
+    // batch of conversions into another tool rather than for a human to read.
+    Csv,
 }
 
 impl Display for ConversionFmt
@@ -36,7 +45,167 @@ impl Display for ConversionFmt
         ConversionFmt::Short => write!(f, "s: short / value only"),
         ConversionFmt::Desc => write!(f, "d: descriptive / value and output unit"),
         ConversionFmt::Long => write!(f, "l: long / input and output values and units"),
+        ConversionFmt::Csv => write!(f, "c: csv / input_value,from_unit,to_unit,result,error"),
+        }
+    }
+}
+
+// How a conversion's numeric result is rendered: plain decimal, or
+// exponential ('1.23e4' style). Orthogonal to 'ConversionFmt', which only
+// controls what's printed alongside the number.
+#[derive(Debug, Copy, Clone)]
+pub enum Notation
+{
+    Standard,
+    Scientific,
+}
+
+// A parsed 'format' command argument: the legacy single-letter/name layout,
+// plus an optional notation and precision, ex. "short:sci:6" or "desc:fixed:2".
+// Kept as its own type (rather than folding parsing into the 'format' match
+// arm in 'Interpreter::interpret') so the grammar stays in one place as more
+// fields get added later.
+#[derive(Debug, Copy, Clone)]
+pub struct FormatSpec
+{
+    pub layout: ConversionFmt,
+    pub notation: Notation,
+    // significant figures to render, in either notation (see 'format_value'); 'None' leaves it to
+    // Rust's default 'Display'/'LowerExp' rendering.
+    pub precision: Option<usize>,
+}
+
+#[derive(Debug)]
+pub enum FormatSpecError
+{
+    UnknownLayout(String),
+    UnknownNotation(String),
+    BadPrecision(String),
+    TooManyFields(String),
+}
+
+impl Error for FormatSpecError
+{
+    fn description(&self) -> &str
+    {
+        match *self
+        {
+        FormatSpecError::UnknownLayout(..) => "unknown format layout",
+        FormatSpecError::UnknownNotation(..) => "unknown number notation",
+        FormatSpecError::BadPrecision(..) => "precision is not a whole number",
+        FormatSpecError::TooManyFields(..) => "too many fields in format spec",
+        }
+    }
+}
+
+impl Display for FormatSpecError
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result
+    {
+        match *self
+        {
+        FormatSpecError::UnknownLayout(ref field) => write!(f, "{}: \'{}\'", self.description(), field),
+        FormatSpecError::UnknownNotation(ref field) => write!(f, "{}: \'{}\'", self.description(), field),
+        FormatSpecError::BadPrecision(ref field) => write!(f, "{}: \'{}\'", self.description(), field),
+        FormatSpecError::TooManyFields(ref field) => write!(f, "{}: \'{}\'", self.description(), field),
+        }
+    }
+}
+
+impl FromStr for FormatSpec
+{
+    type Err = FormatSpecError;
+
+    // Tokenizes on ':' and validates each field in turn: layout, then an
+    // optional notation, then an optional precision. Legacy single-letter
+    // names ('s', 'd', 'l') are still accepted with nothing following them.
+    fn from_str(spec: &str) -> Result<FormatSpec, FormatSpecError>
+    {
+        let mut fields = spec.split(':');
+
+        let layout = match fields.next().unwrap()
+        {
+        "s" | "short" => ConversionFmt::Short,
+        "d" | "desc" => ConversionFmt::Desc,
+        "l" | "long" => ConversionFmt::Long,
+        "c" | "csv" => ConversionFmt::Csv,
+        other => return Err(FormatSpecError::UnknownLayout(other.to_string())),
+        };
+
+        let notation = match fields.next()
+        {
+        None => Notation::Standard,
+        Some("fixed") => Notation::Standard,
+        Some("sci") => Notation::Scientific,
+        Some(other) => return Err(FormatSpecError::UnknownNotation(other.to_string())),
+        };
+
+        let precision = match fields.next()
+        {
+        None => None,
+        Some(digits) => match digits.parse::<usize>()
+            {
+            Ok(digits) => Some(digits),
+            Err(..) => return Err(FormatSpecError::BadPrecision(digits.to_string())),
+            },
+        };
+
+        if fields.next().is_some()
+        {
+            return Err(FormatSpecError::TooManyFields(spec.to_string()));
+        }
+
+        Ok(FormatSpec { layout: layout, notation: notation, precision: precision })
+    }
+}
+
+// Rounds 'value' to 'sig_figs' significant figures, the standard
+// log10-and-rescale recurrence (see 'exec::round_to_sig_figs' for the
+// original). 0 and non-finite values are returned unchanged since they
+// have no meaningful magnitude to round against.
+fn round_to_sig_figs(value: f64, sig_figs: usize) -> f64
+{
+    if value == 0.0 || !value.is_finite()
+    {
+        return value;
+    }
+
+    let sig_figs = sig_figs.max(1) as i32;
+    let magnitude = value.abs().log10().floor() as i32;
+    let scale = 10f64.powi(sig_figs - 1 - magnitude);
+
+    (value * scale).round() / scale
+}
+
+// Renders 'value' per 'notation'/'precision' for a conversion result. The
+// one place that interprets those two fields, so 'Display for Conversion'
+// and 'fmt_csv' can't drift out of sync with each other. 'precision' is a
+// significant-figure count, not a decimal-place count, in either notation:
+// in standard notation the value is rounded to that many sig figs and
+// printed with exactly as many decimal places as that demands; in
+// scientific notation a normalized mantissa's leading digit is already one
+// significant figure, so the mantissa gets 'precision - 1' decimal places.
+fn format_value(value: f64, notation: Notation, precision: Option<usize>) -> String
+{
+    match (notation, precision)
+    {
+    (Notation::Standard, None) => format!("{}", value),
+    (Notation::Standard, Some(sig_figs)) => {
+        let rounded = round_to_sig_figs(value, sig_figs);
+
+        if rounded == 0.0 || !rounded.is_finite()
+        {
+            return format!("{}", rounded);
         }
+
+        let sig_figs = sig_figs.max(1) as i32;
+        let magnitude = rounded.abs().log10().floor() as i32;
+        let decimal_places = (sig_figs - 1 - magnitude).max(0) as usize;
+
+        format!("{:.*}", decimal_places, rounded)
+    },
+    (Notation::Scientific, None) => format!("{:e}", value),
+    (Notation::Scientific, Some(sig_figs)) => format!("{:.*e}", sig_figs.max(1) - 1, value),
     }
 }
 
@@ -45,21 +214,30 @@ pub struct Conversion
 {
     from_prefix: char,
     to_prefix: char,
-    pub from_alias: String,
-    pub to_alias: String,
-    pub from_tag: Option<String>,
-    pub to_tag: Option<String>,
+    // carried as the interned 'Rc<str>' handle 'UnitExpr' hands out, so
+    // building a batch of 'Conversion's from one 'ConvPrimitive' only bumps
+    // refcounts instead of copying the alias text for every output unit.
+    pub from_alias: Rc<str>,
+    pub to_alias: Rc<str>,
+    pub from_tag: Option<Rc<str>>,
+    pub to_tag: Option<Rc<str>>,
     pub from: Option<Rc<Unit>>,
     pub to: Option<Rc<Unit>>,
     pub input: f64,
     pub result: Result<f64, ConversionError>,
     pub format: ConversionFmt,
+    pub notation: Notation,
+    pub precision: Option<usize>,
+    // aliases 'UnitDatabase::search' ranked closest to whichever of
+    // 'from_alias'/'to_alias' caused a 'ConversionError::UnitNotFound';
+    // empty otherwise. Let's the error message suggest a correction.
+    pub suggestions: Vec<Rc<String>>,
 }
 
 impl Conversion
 {
-    fn new(input_prefix: char, input_alias: String, input_tag: Option<String>,
-        output_prefix: char, output_alias: String, output_tag: Option<String>,
+    fn new(input_prefix: char, input_alias: Rc<str>, input_tag: Option<Rc<str>>,
+        output_prefix: char, output_alias: Rc<str>, output_tag: Option<Rc<str>>,
         input_val: f64) -> Conversion
     {
         Conversion {
@@ -74,6 +252,61 @@ impl Conversion
             input: input_val,
             result: Ok(1.0),
             format: ConversionFmt::Desc,
+            notation: Notation::Standard,
+            precision: None,
+            suggestions: Vec::new(),
+        }
+    }
+}
+
+impl Conversion
+{
+    // Short, plain-text description of an error for the 'Csv' record's
+    // 'error' field, as opposed to the "Conversion error: ..." prose the
+    // other formats render below.
+    fn csv_error(&self, err: &ConversionError) -> String
+    {
+        match *err
+        {
+        ConversionError::OutOfRange(in_or_out) => {
+            format!("{} value out of range", if in_or_out == OUTPUT { "output" } else { "input" })
+        },
+        ConversionError::UnitNotFound(in_or_out) => {
+            format!("no unit called '{}' was found",
+                if in_or_out == OUTPUT { &self.to_alias } else { &self.from_alias })
+        },
+        ConversionError::TypeMismatch => {
+            format!("'{}' is a {} and '{}' is a {}",
+                self.from_alias, self.from.as_ref().unwrap().unit_type,
+                self.to_alias, self.to.as_ref().unwrap().unit_type)
+        },
+        }
+    }
+
+    fn fmt_csv(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        let mut from_prefix = String::with_capacity(1);
+        if self.from_prefix != NO_PREFIX
+        {
+            from_prefix.push(self.from_prefix);
+        }
+
+        let mut to_prefix = String::with_capacity(1);
+        if self.to_prefix != NO_PREFIX
+        {
+            to_prefix.push(self.to_prefix);
+        }
+
+        match self.result
+        {
+        Ok(ref output) => {
+            write!(f, "{},{}{},{}{},{},", self.input, from_prefix, self.from_alias,
+                to_prefix, self.to_alias, format_value(*output, self.notation, self.precision))
+        },
+        Err(ref err) => {
+            write!(f, "{},{}{},{}{},,{}", self.input, from_prefix, self.from_alias,
+                to_prefix, self.to_alias, self.csv_error(err))
+        },
         }
     }
 }
@@ -82,9 +315,16 @@ impl Display for Conversion
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
     {
+        if let ConversionFmt::Csv = self.format
+        {
+            return self.fmt_csv(f);
+        }
+
         match self.result
         {
         Ok(ref output) => {
+            let output = format_value(*output, self.notation, self.precision);
+
             match self.format
             {
             ConversionFmt::Short => write!(f, "{}", output),
@@ -139,7 +379,27 @@ impl Display for Conversion
                     else
                     {
                         &self.from_alias
-                    })
+                    })?;
+
+                if !self.suggestions.is_empty()
+                {
+                    write!(f, ". Did you mean: ")?;
+
+                    for (index, suggestion) in self.suggestions.iter().enumerate()
+                    {
+                        if index > 0
+                        {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "\'{}\'", suggestion)?;
+                    }
+
+                    write!(f, "?")
+                }
+                else
+                {
+                    Ok(())
+                }
             },
             &ConversionError::TypeMismatch =>
                 write!(f, "Conversion error: input and output types differ.\
@@ -185,8 +445,8 @@ impl Display for Conversion
  *   6. invert result if necessary
  *   7. scale result using prefix and dimensions
  */
-pub fn convert(input: f64, from_prefix: char, from: String, from_tag: Option<String>,
-    to_prefix: char, to: String, to_tag: Option<String>, units: &UnitDatabase) -> Conversion
+pub fn convert(input: f64, from_prefix: char, from: Rc<str>, from_tag: Option<Rc<str>>,
+    to_prefix: char, to: Rc<str>, to_tag: Option<Rc<str>>, units: &UnitDatabase) -> Conversion
 {
     //println!("from_tag: {:?}    to_tag: {:?}", from_tag, to_tag);
     let mut conversion = Conversion::new(from_prefix,from, from_tag,
@@ -201,8 +461,8 @@ pub fn convert(input: f64, from_prefix: char, from: String, from_tag: Option<Str
         return conversion;
     }
 
-    conversion.from = units.query(&conversion.from_alias, conversion.from_tag.as_ref());
-    conversion.to = units.query(&conversion.to_alias, conversion.to_tag.as_ref());
+    conversion.from = units.query(&conversion.from_alias, conversion.from_tag.as_ref().map(|tag| &**tag));
+    conversion.to = units.query(&conversion.to_alias, conversion.to_tag.as_ref().map(|tag| &**tag));
 
     if conversion.from.is_none()
     {
@@ -214,6 +474,21 @@ pub fn convert(input: f64, from_prefix: char, from: String, from_tag: Option<Str
     }
     if conversion.result.is_err()
     {
+        // suggest corrections for whichever alias is actually named in the
+        // error above; if both were unresolved, 'to_alias' wins since it
+        // overwrote 'from_alias's error in the checks above
+        let unresolved_alias = match conversion.result
+        {
+            Err(ConversionError::UnitNotFound(OUTPUT)) => conversion.to_alias.clone(),
+            Err(ConversionError::UnitNotFound(INPUT)) => conversion.from_alias.clone(),
+            _ => unreachable!(),
+        };
+
+        conversion.suggestions = units.search(&unresolved_alias, SUGGESTION_LIMIT)
+            .into_iter()
+            .map(|(alias, _)| alias)
+            .collect();
+
         return conversion;
     }
 
@@ -298,7 +573,7 @@ pub fn convert_all(conv_primitive: ConvPrimitive, units: &UnitDatabase) -> Vec<C
             all_conversions.push(
                 convert(value_expr.value,
                         conv_primitive.input_unit.prefix, conv_primitive.input_unit.alias.clone().unwrap(), conv_primitive.input_unit.tag.clone(),
-                        output_unit.clone().prefix, output_unit.clone().alias.unwrap(), output_unit.tag.clone(),
+                        output_unit.prefix, output_unit.alias.clone().unwrap(), output_unit.tag.clone(),
                         units)
             );
         }
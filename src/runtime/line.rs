@@ -0,0 +1,173 @@
+/* line.rs (runtime)
+ * ===
+ * Abstracts the interpreter's input side behind 'LineSource' so an
+ * interactive front-end can supply history and completion without the rest
+ * of 'runtime' having to tell that apart from piped or 'source'd file input.
+ * 'Interpreter::input_stack' (see runtime/mod.rs) holds a stack of boxed
+ * 'LineSource's; the stream-based 'using_streams' path keeps working
+ * unchanged since any 'BufRead' already implements 'LineSource' below.
+ *
+ * This file is a part of:
+ *
+ * Yucon - General Purpose Unit Converter
+ * Copyright (C) 2016-2017  Blaine Murphy
+ *
+ * This program is free software: you can redistribute it and/or modify it under the terms
+ * of the GNU General Public License as published by the Free Software Foundation, either
+ * version 3 of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+ * without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+ * See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+extern crate linefeed;
+
+use std::env;
+use std::io;
+use std::io::BufRead;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use self::linefeed::{Completer, Completion, Interface, Prompter, ReadResult, Terminal};
+
+use ::runtime::units::UnitDatabase;
+
+// Every keyword 'Interpreter::interpret' matches on before falling through
+// to treating the line as a conversion. Kept here rather than imported so
+// this module doesn't have to reach back into 'runtime's private match arms
+// just to keep a completion list in sync; a keyword added there should be
+// added here too.
+static KEYWORDS: [&'static str; 12] =
+    ["exit", "format", "input_unit", "output_unit", "value", "version", "help", "search", "list", "define",
+     "source", "include"];
+
+pub trait LineSource
+{
+    // Same contract as 'BufRead::read_line': appends one line (including
+    // its newline, if any) to 'buf' and returns the number of bytes read,
+    // with 0 meaning the source is exhausted.
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>;
+}
+
+impl<T: BufRead> LineSource for T
+{
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>
+    {
+        BufRead::read_line(self, buf)
+    }
+}
+
+// Offers the interpreter's command keywords at the start of a line, and unit
+// aliases at the start of a line or after a 'to'/'in' position, where a unit
+// expression can start in a conversion line ("<value> in <unit>",
+// "<value> <unit> to <unit>").
+struct UnitCompleter
+{
+    aliases: Vec<Rc<String>>,
+}
+
+impl<Term: Terminal> Completer<Term> for UnitCompleter
+{
+    fn complete(&self, word: &str, prompter: &Prompter<Term>,
+        start: usize, _end: usize) -> Option<Vec<Completion>>
+    {
+        let preceding = prompter.buffer()[..start].trim_end();
+        let after_unit_position = preceding.is_empty()
+            || preceding.ends_with("to")
+            || preceding.ends_with("in");
+
+        let mut completions = Vec::new();
+
+        if after_unit_position
+        {
+            for alias in self.aliases.iter()
+            {
+                if alias.starts_with(word)
+                {
+                    completions.push(Completion::simple((**alias).clone()));
+                }
+            }
+        }
+
+        if preceding.is_empty()
+        {
+            for keyword in KEYWORDS.iter()
+            {
+                if keyword.starts_with(word)
+                {
+                    completions.push(Completion::simple(keyword.to_string()));
+                }
+            }
+        }
+
+        if completions.is_empty() { None } else { Some(completions) }
+    }
+}
+
+// Where command history is remembered between sessions, mirroring
+// 'config::find_and_make_cfg's '$HOME/.yucon/' convention.
+fn history_path() -> Option<PathBuf>
+{
+    let mut path = env::home_dir()?;
+    path.push(".yucon");
+    path.push("history");
+    Some(path)
+}
+
+// 'LineSource' backed by a 'linefeed' editor: arrow-key history, unit/
+// keyword completion, and the history file loaded on construction /
+// saved again after every line read.
+pub struct Repl
+{
+    interface: Interface<linefeed::DefaultTerminal>,
+    history_path: Option<PathBuf>,
+}
+
+impl Repl
+{
+    pub fn new(prompt: &str, units: &UnitDatabase) -> io::Result<Repl>
+    {
+        let interface = Interface::new("yucon")?;
+        interface.set_prompt(prompt)?;
+        interface.set_completer(Arc::new(UnitCompleter { aliases: units.all_aliases() }));
+
+        let history_path = history_path();
+
+        if let Some(ref path) = history_path
+        {
+            let _ = interface.load_history(path);
+        }
+
+        Ok(Repl { interface: interface, history_path: history_path })
+    }
+}
+
+impl LineSource for Repl
+{
+    fn read_line(&mut self, buf: &mut String) -> io::Result<usize>
+    {
+        match self.interface.read_line()?
+        {
+            ReadResult::Input(line) => {
+                self.interface.add_history(line.clone());
+
+                if let Some(ref path) = self.history_path
+                {
+                    let _ = self.interface.save_history(path);
+                }
+
+                buf.push_str(&line);
+                buf.push('\n');
+                Ok(buf.len())
+            },
+            // Eof / Signal: treat the same as a 0 byte 'read_line', the
+            // 'BufRead' convention for "this source is exhausted"
+            _ => Ok(0),
+        }
+    }
+}
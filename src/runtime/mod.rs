@@ -23,30 +23,44 @@
  */
 
 pub mod convert;
+pub mod line;
 pub mod parse;
 mod state;
 pub mod units;
 
 use std::io;
 use std::io::Read;
-use std::io::BufRead;
 use std::io::BufReader;
+use std::fs::File;
 use std::fmt;
 use std::fmt::Display;
 use std::fmt::Write;
 use std::error::Error;
+use std::rc::Rc;
 
 use ::utils::*;
-use ::runtime::parse::ConvPrimitive;
+use ::runtime::parse::{to_conv_primitive, ConvPrimitive};
 use ::runtime::parse::number::{parse_number_expr, NumberExpr};
 use ::runtime::parse::unit::{parse_unit_expr, UnitExpr};
-use ::runtime::convert::{Conversion, ConversionFmt, ConversionError};
-use runtime::units::UnitDatabase;
+use ::runtime::convert::{convert_all, Conversion, ConversionFmt, ConversionError, FormatSpec, Notation};
+use runtime::units::{DefineSpec, Unit, UnitDatabase, UNIT_TYPES};
 use runtime::state::Options;
+use runtime::line::{LineSource, Repl};
 use std::io::Write;
+use std::str::FromStr;
 use runtime::units::config::load_units_list;
 
-static NONLITERAL_RECALL_MSG: &'static str = "recall variables must be literals";
+pub(crate) static NONLITERAL_RECALL_MSG: &'static str = "recall variables must be literals";
+
+// Caps how many 'source'd files can be nested inside one another, so a
+// script that (directly or transitively) includes itself fails cleanly
+// instead of recursing forever.
+const MAX_INCLUDE_DEPTH: usize = 16;
+
+// How many candidates 'search' (the command, and the suggestion lookup on a
+// failed conversion) shows at most, so a query that matches broadly doesn't
+// dump the whole database.
+const SEARCH_RESULT_LIMIT: usize = 10;
 
 #[derive(Debug)]
 pub enum InterpretErr
@@ -266,29 +280,68 @@ impl Boostrapper
     */
 }
 
-pub struct Interpreter<I, O> where I: Read, O: io::Write
+pub struct Interpreter<O> where O: io::Write
 {
     pub format: ConversionFmt,
-    input_stream: BufReader<I>,
+    // notation/precision for rendering a conversion's result value, set
+    // together with 'format' by the 'format' command's 'FormatSpec'.
+    pub notation: Notation,
+    pub precision: Option<usize>,
+    // a stack rather than a single fixed stream so a 'source'/'include'
+    // command can push a script file's lines in front of whatever is
+    // already being read, then let it fall back there on EOF. The stream
+    // the caller hands 'using_streams'/'using_repl' is always the
+    // bottom-most entry and is never popped. Boxed as 'LineSource' rather
+    // than 'BufRead' so an interactive front-end ('line::Repl') can sit in
+    // the same stack as piped/'source'd file input.
+    input_stack: Vec<Box<LineSource>>,
     output_stream: O,
     input_value: Option<f64>,
-    input_unit: Option<String>,
-    output_unit: Option<String>,
+    input_unit: Option<Rc<str>>,
+    output_unit: Option<Rc<str>>,
 }
 
-impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
+impl <O> Interpreter<O> where O: io::Write
 {
-    pub fn using_streams(istream: I, ostream: O) -> Interpreter<I, O>
+    // Seeds session state ('format' and the recall defaults) from 'opts' so
+    // presets given on the command line (see 'Options::get_opts') are in
+    // place before the first line is read.
+    pub fn using_streams<I: Read + 'static>(istream: I, ostream: O, opts: &Options) -> Interpreter<O>
     {
-        Interpreter { format: ConversionFmt::Desc,
-                      input_stream: BufReader::new(istream),
+        let mut input_stack: Vec<Box<LineSource>> = Vec::with_capacity(1);
+        input_stack.push(Box::new(BufReader::new(istream)));
+
+        Interpreter { format: opts.format(),
+                      notation: Notation::Standard,
+                      precision: None,
+                      input_stack: input_stack,
                       output_stream: ostream,
-                      input_value: None,
-                      input_unit: None,
-                      output_unit: None,
+                      input_value: opts.preset_value(),
+                      input_unit: opts.preset_input_unit(),
+                      output_unit: opts.preset_output_unit(),
         }
     }
 
+    // Interactive counterpart to 'using_streams': backs the bottom of the
+    // input stack with a 'line::Repl' instead of a plain stream, so typing
+    // at a tty gets history and completion sourced from 'units'.
+    pub fn using_repl(units: &UnitDatabase, ostream: O, opts: &Options) -> io::Result<Interpreter<O>>
+    {
+        let repl = Repl::new("> ", units)?;
+        let mut input_stack: Vec<Box<LineSource>> = Vec::with_capacity(1);
+        input_stack.push(Box::new(repl));
+
+        Ok(Interpreter { format: opts.format(),
+                          notation: Notation::Standard,
+                          precision: None,
+                          input_stack: input_stack,
+                          output_stream: ostream,
+                          input_value: opts.preset_value(),
+                          input_unit: opts.preset_input_unit(),
+                          output_unit: opts.preset_output_unit(),
+        })
+    }
+
     /* Gets the next line from the input stream and interpets as either a
      * conversion or a command. If it is a command ie beginning in a program
      * internal keyword then the command will attempt to be executed and a
@@ -299,20 +352,40 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
      * Returns:
      *
      */
-    pub fn interpret(&mut self) -> Result<Vec<TokenType>, InterpretErr>
+    pub fn interpret(&mut self, units: &mut UnitDatabase) -> Result<Vec<TokenType>, InterpretErr>
     {
         let mut raw_line = String::with_capacity(80); // std terminal width
-        let bytes_read = self.input_stream.read_line(&mut raw_line);
 
-        if bytes_read.is_err()
-        {
-            write!(self.output_stream, "fatal input stream error: {}", bytes_read.err().unwrap());
-            return Err(InterpretErr::ExitSig);
-        }
-        else if bytes_read.unwrap() == 0
+        loop
         {
-            // end of input stream reached. exit
-            return Err(InterpretErr::ExitSig);
+            let bytes_read = match self.input_stack.last_mut()
+            {
+                Some(stream) => stream.read_line(&mut raw_line),
+                // the caller's own stream is never popped, so this is only
+                // reached once that stream itself has run dry
+                None => return Err(InterpretErr::ExitSig),
+            };
+
+            if bytes_read.is_err()
+            {
+                write!(self.output_stream, "fatal input stream error: {}", bytes_read.err().unwrap());
+                return Err(InterpretErr::ExitSig);
+            }
+            else if bytes_read.unwrap() == 0
+            {
+                // this source is exhausted; if it was a 'source'd file, fall
+                // back to whatever included it instead of ending the session
+                self.input_stack.pop();
+
+                if self.input_stack.is_empty()
+                {
+                    return Err(InterpretErr::ExitSig);
+                }
+
+                continue;
+            }
+
+            break;
         }
 
         let mut line_checker = LineCheck::new();
@@ -348,21 +421,145 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
             {
                 let value = next_tok.unwrap();
 
-                let next_fmt = match value.peek().as_ref()
+                let spec = match FormatSpec::from_str(value.peek())
                 {
-                "s" => ConversionFmt::Short,
-                "d" => ConversionFmt::Desc,
-                "l" => ConversionFmt::Long,
-                _ => return Err(InterpretErr::InvalidState(value.peek().clone())),
+                    Ok(spec) => spec,
+                    Err(err) => return Err(InterpretErr::InvalidState(format!("{}", err))),
                 };
 
-                self.format = next_fmt;
+                self.format = spec.layout;
+                self.notation = spec.notation;
+                self.precision = spec.precision;
                 cmd_result = InterpretErr::CmdSuccess("Okay.".to_string());
             }
         },
         "help" => {
             cmd_result = InterpretErr::HelpSig;
         },
+        "search" => {
+            let next_tok = tokens_iter.next();
+
+            if next_tok.is_none()
+            {
+                return Err(InterpretErr::IncompleteErr);
+            }
+
+            let term = next_tok.unwrap().peek();
+            let matches = units.search(term, SEARCH_RESULT_LIMIT);
+
+            if matches.is_empty()
+            {
+                cmd_result = InterpretErr::CmdSuccess(format!("no units matching '{}'", term));
+            }
+            else
+            {
+                let mut listing = String::with_capacity(40 * matches.len());
+
+                for (alias, unit) in matches.iter()
+                {
+                    write!(&mut listing, "{} -> {} ({})\n", alias, unit.common_name, unit.unit_type);
+                }
+                listing.pop(); // drop the trailing newline; 'newline' supplies the final one
+
+                cmd_result = InterpretErr::CmdSuccess(listing);
+            }
+        },
+        "list" => {
+            let grouped = units.list_units();
+            let mut listing = String::with_capacity(80 * grouped.len());
+
+            for (unit, aliases) in grouped.iter()
+            {
+                let alias_list: Vec<&str> = aliases.iter().map(|alias| alias.as_str()).collect();
+                write!(&mut listing, "{} ({}): {}\n", unit.common_name, unit.unit_type, alias_list.join(", "));
+            }
+            listing.pop(); // drop the trailing newline; 'newline' supplies the final one
+
+            cmd_result = InterpretErr::CmdSuccess(listing);
+        },
+        "define" => {
+            let next_tok = tokens_iter.next();
+
+            if next_tok.is_none()
+            {
+                return Err(InterpretErr::IncompleteErr);
+            }
+
+            let spec = match DefineSpec::from_str(next_tok.unwrap().peek())
+            {
+                Ok(spec) => spec,
+                Err(err) => return Err(InterpretErr::InvalidState(format!("{}", err))),
+            };
+
+            let unit_type = match UNIT_TYPES.iter().find(|candidate| candidate.eq_ignore_ascii_case(&spec.unit_type))
+            {
+                Some(found) => *found,
+                None => return Err(InterpretErr::InvalidState(
+                        format!("unrecognized unit type: '{}'", spec.unit_type))),
+            };
+
+            let aliases: Vec<Rc<String>> = spec.aliases.iter().map(|alias| Rc::new(alias.clone())).collect();
+            let tags: Vec<Rc<String>> = Vec::new();
+
+            let new_unit = Unit { common_name: Rc::new(spec.common_name.clone()),
+                                   conv_factor: spec.conv_factor,
+                                   dimensions: 1,
+                                   inverse: false,
+                                   unit_type: unit_type,
+                                   zero_point: 0.0,
+                                   has_aliases: !aliases.is_empty(),
+                                   has_tags: false };
+
+            match units.add(new_unit, &aliases, &tags)
+            {
+                Some(rejected) => {
+                    // 'add' only hands the rejected unit back, not which
+                    // alias collided, so ask the same collision check what
+                    // it found to name it in the error
+                    let conflict = match units.check_collisions(&rejected, &aliases, &tags)
+                    {
+                        Some((_, name)) => (*name).clone(),
+                        None => spec.common_name.clone(),
+                    };
+
+                    return Err(InterpretErr::InvalidState(
+                            format!("'{}' is already a registered unit or alias", conflict)));
+                },
+                None => {
+                    cmd_result = match units.save_to_file()
+                    {
+                        Ok(()) => InterpretErr::CmdSuccess("Okay.".to_string()),
+                        Err(err) => InterpretErr::CmdSuccess(
+                                format!("unit added, but failed to save units.yaml: {}", err)),
+                    };
+                },
+            };
+        },
+        keyword @ "source" | keyword @ "include" => {
+            let next_tok = tokens_iter.next();
+
+            if next_tok.is_none()
+            {
+                return Err(InterpretErr::IncompleteErr);
+            }
+
+            let path = next_tok.unwrap().peek();
+
+            if self.input_stack.len() >= MAX_INCLUDE_DEPTH
+            {
+                return Err(InterpretErr::InvalidState(
+                        format!("'{}' nested too deeply (max depth {})", keyword, MAX_INCLUDE_DEPTH)));
+            }
+
+            let file = match File::open(path)
+            {
+                Ok(file) => file,
+                Err(err) => return Err(InterpretErr::InvalidState(format!("{}: {}", path, err))),
+            };
+
+            self.input_stack.push(Box::new(BufReader::new(file)));
+            cmd_result = InterpretErr::CmdSuccess("Okay.".to_string());
+        },
         keyword @ "input_unit" | keyword @ "output_unit" => {
             let is_input = keyword.starts_with("input");
             let next_tok = tokens_iter.next();
@@ -380,7 +577,7 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 
                 cmd_result = InterpretErr::CmdSuccess(
                     if value.is_none() { "[not set]".to_string() }
-                    else { value.clone().unwrap() });
+                    else { value.clone().unwrap().to_string() });
             }
             else
             {
@@ -538,6 +735,8 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
 
     pub fn update_recall(&mut self, conversions: &Vec<Conversion>)
     {
+        let mut last_success: Option<&Conversion> = None;
+
         for conversion in conversions.iter()
         {
             match conversion.result
@@ -575,9 +774,21 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
                 self.input_value = Some(conversion.input);
                 self.input_unit = Some(conversion.from_alias.clone());
                 self.output_unit = Some(conversion.to_alias.clone());
+                last_success = Some(conversion);
             },
             };
         }
+
+        // with several comma-separated output targets in one line, a later
+        // target's own error shouldn't clobber the alias recall picked up
+        // from an earlier, successful one - the last conversion that
+        // actually succeeded always has the final say over recall state.
+        if let Some(conversion) = last_success
+        {
+            self.input_value = Some(conversion.input);
+            self.input_unit = Some(conversion.from_alias.clone());
+            self.output_unit = Some(conversion.to_alias.clone());
+        }
     }
 
     pub fn publish<T>(&mut self, element: &T, mesg: &Option<String>) where T: Display
@@ -599,4 +810,60 @@ impl <I, O> Interpreter<I, O> where I: Read, O: io::Write
                if cfg!(target_os="windows") { "\r\n" } else { "\n" }
         );
     }
-}
\ No newline at end of file
+
+    /* Drives the interpreter over the rest of its input stream, converting
+     * and publishing one line at a time until the stream runs dry or the
+     * user issues 'exit'. This is the batch / piped counterpart to an
+     * interactive front-end calling 'interpret' by hand for one line at a
+     * time - the loop 'Options::get_opts' turning off 'interactive' for a
+     * one-shot or piped invocation implies, but that no caller has driven
+     * yet.
+     */
+    pub fn run(&mut self, units: &mut UnitDatabase)
+    {
+        loop
+        {
+            let tokens = match self.interpret(units)
+            {
+                Ok(tokens) => tokens,
+                Err(InterpretErr::ExitSig) => break,
+                Err(InterpretErr::BlankLine) => continue,
+                Err(err) => {
+                    self.publish(&err, &None);
+                    self.newline();
+                    continue;
+                },
+            };
+
+            let mut conv_primitive = match to_conv_primitive(&tokens)
+            {
+                Ok(primitive) => primitive,
+                Err(err) => {
+                    self.publish(&err, &None);
+                    self.newline();
+                    continue;
+                },
+            };
+
+            if let Some(err) = self.perform_recall(&mut conv_primitive)
+            {
+                self.publish(&err, &None);
+                self.newline();
+                continue;
+            }
+
+            let mut conversions = convert_all(conv_primitive, units);
+
+            for conversion in conversions.iter_mut()
+            {
+                conversion.format = self.format;
+                conversion.notation = self.notation;
+                conversion.precision = self.precision;
+                self.publish(conversion, &None);
+                self.newline();
+            }
+
+            self.update_recall(&conversions);
+        }
+    }
+}
@@ -0,0 +1,122 @@
+/* combinator.rs (runtime::parse)
+ * ===
+ * Small parser-combinator primitives over a 'TokenType' stream, the
+ * 'runtime' parser's equivalent of the top level 'combinator.rs' (which
+ * operates over raw '&str' for the older 'exec'/'parse' grammar). Each
+ * parser is a closure that takes the slice of tokens remaining to be
+ * consumed together with a 'Position' to blame if it runs out of tokens,
+ * and returns the new remaining slice and whatever value it parsed, or a
+ * 'SyntaxError' labeled with what was expected there.
+ *
+ * This file is a part of:
+ *
+ * Yucon - General Purpose Unit Converter
+ * Copyright (C) 2016-2017  Blaine Murphy
+ *
+ * This program is free software: you can redistribute it and/or modify it under the terms
+ * of the GNU General Public License as published by the Free Software Foundation, either
+ * version 3 of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+ * without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+ * See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use ::utils::{Position, SyntaxError, TokenType};
+
+pub type ParseResult<'t, T> = Result<(&'t [TokenType], T), SyntaxError>;
+pub type Parser<'t, T> = Box<Fn(&'t [TokenType], Position) -> ParseResult<'t, T> + 't>;
+
+// Consumes one token if 'pred' accepts it. 'expected' becomes the message
+// on the 'SyntaxError::Expected' raised when 'pred' rejects the next token,
+// or there is no next token at all (in which case the error points at
+// 'end_pos', the position just past the last real token in the expression).
+pub fn satisfies<'t, F>(expected: &'static str, pred: F) -> Parser<'t, TokenType>
+    where F: Fn(&TokenType) -> bool + 't
+{
+    Box::new(move |tokens: &'t [TokenType], end_pos: Position| {
+        match tokens.split_first()
+        {
+        Some((head, rest)) if pred(head) => Ok((rest, head.clone())),
+        Some((head, _)) => Err(SyntaxError::Expected(head.position().range(), expected.to_string())),
+        None => Err(SyntaxError::Expected(end_pos.range(), expected.to_string())),
+        }
+    })
+}
+
+// Matches any single token, unconditionally. Combined with 'many' this is
+// how the grammar asserts "nothing more" at the end of an expression.
+pub fn any_token<'t>() -> Parser<'t, TokenType>
+{
+    satisfies("no more tokens", |_| true)
+}
+
+// Runs 'first' then 'second' against whatever 'first' leaves behind,
+// folding both results into a tuple.
+pub fn seq<'t, A: 't, B: 't>(first: Parser<'t, A>, second: Parser<'t, B>) -> Parser<'t, (A, B)>
+{
+    Box::new(move |tokens: &'t [TokenType], end_pos: Position| {
+        let (rest, a) = try!(first(tokens, end_pos));
+        let (rest, b) = try!(second(rest, end_pos));
+        Ok((rest, (a, b)))
+    })
+}
+
+// Tries 'first'; if it fails without consuming a successful match, tries
+// 'second' from the same starting point. Whichever alternative matches the
+// grammar picks wins; if neither does, 'second's error is reported since it
+// was the last (and therefore most specific) expectation in play.
+pub fn alt<'t, T: 't>(first: Parser<'t, T>, second: Parser<'t, T>) -> Parser<'t, T>
+{
+    Box::new(move |tokens: &'t [TokenType], end_pos: Position| {
+        match first(tokens, end_pos)
+        {
+        Ok(result) => Ok(result),
+        Err(_) => second(tokens, end_pos),
+        }
+    })
+}
+
+// Tries 'parser'; on failure, succeeds with 'None' and consumes nothing.
+pub fn opt<'t, T: 't>(parser: Parser<'t, T>) -> Parser<'t, Option<T>>
+{
+    Box::new(move |tokens: &'t [TokenType], end_pos: Position| {
+        match parser(tokens, end_pos)
+        {
+        Ok((rest, value)) => Ok((rest, Some(value))),
+        Err(_) => Ok((tokens, None)),
+        }
+    })
+}
+
+// Transforms a successful parse's value with 'f'; position and errors pass
+// through untouched.
+pub fn map<'t, T: 't, U: 't, F>(parser: Parser<'t, T>, f: F) -> Parser<'t, U>
+    where F: Fn(T) -> U + 't
+{
+    Box::new(move |tokens: &'t [TokenType], end_pos: Position| {
+        let (rest, value) = try!(parser(tokens, end_pos));
+        Ok((rest, f(value)))
+    })
+}
+
+// Runs 'parser' repeatedly, collecting its successes, until it fails or the
+// stream is exhausted. Always succeeds, possibly with an empty 'Vec'.
+pub fn many<'t, T: 't>(parser: Parser<'t, T>) -> Parser<'t, Vec<T>>
+{
+    Box::new(move |tokens: &'t [TokenType], end_pos: Position| {
+        let mut rest = tokens;
+        let mut values = Vec::new();
+
+        while let Ok((new_rest, value)) = parser(rest, end_pos)
+        {
+            rest = new_rest;
+            values.push(value);
+        }
+
+        Ok((rest, values))
+    })
+}
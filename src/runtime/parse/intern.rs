@@ -0,0 +1,60 @@
+/* intern.rs
+ * ===
+ * A small string interner for unit aliases and tags, in the spirit of
+ * rhai's 'StringInterner': the first time a spelling is seen it is
+ * heap-allocated once into an 'Rc<str>' and cached; every later occurrence
+ * of that same spelling hands back a clone of the cached handle instead of
+ * allocating again. A batch conversion file repeats the same handful of
+ * unit names thousands of times, so this turns what would be one
+ * allocation per token into one allocation per distinct spelling, and
+ * makes cloning a 'UnitExpr' (done freely once a 'ConvPrimitive' fans out
+ * into several 'Conversion's) the cost of a refcount bump rather than a
+ * string copy.
+ *
+ * This file is a part of:
+ *
+ * Yucon - General Purpose Unit Converter
+ * Copyright (C) 2016-2017  Blaine Murphy
+ *
+ * This program is free software: you can redistribute it and/or modify it under the terms
+ * of the GNU General Public License as published by the Free Software Foundation, either
+ * version 3 of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+ * without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+ * See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::rc::Rc;
+
+thread_local! {
+    // Every alias / tag spelling the process has parsed so far, keyed by its
+    // own text. A 'HashSet' is enough here since we never need to look a
+    // spelling up by anything other than itself.
+    static CACHE: RefCell<HashSet<Rc<str>>> = RefCell::new(HashSet::new());
+}
+
+// Returns the single shared 'Rc<str>' handle for 'text', allocating one and
+// caching it the first time this spelling is seen. Safe to call from
+// anywhere in the 'runtime' parser; the cache lives for the life of the
+// process.
+pub fn intern(text: &str) -> Rc<str>
+{
+    CACHE.with(|cache| {
+        let mut cache = cache.borrow_mut();
+
+        if let Some(existing) = cache.get(text)
+        {
+            return existing.clone();
+        }
+
+        let handle: Rc<str> = Rc::from(text);
+        cache.insert(handle.clone());
+        handle
+    })
+}
@@ -1,3 +1,5 @@
+pub mod combinator;
+pub mod intern;
 pub mod number;
 pub mod unit;
 
@@ -7,14 +9,34 @@ use std::fmt::{Display, Formatter};
 
 use ::utils::*;
 use ::runtime::parse::number::{NumberExpr, parse_number_expr};
-use ::runtime::parse::unit::{UnitExpr, parse_unit_expr};
+use ::runtime::parse::unit::{UnitExpr, parse_unit_expr, parse_unit_expr_list};
 
 #[derive(Debug)]
 pub enum ExprParseError
 {
     Syntax(SyntaxError),
-    BadPrefix(char),
-    EmptyField(String),
+    BadPrefix(char, Position),
+    EmptyField(String, Position),
+    DivByZero(Position),
+    MismatchedParens(Position),
+}
+
+impl ExprParseError
+{
+    // Returns the character span this error should have a caret drawn under,
+    // recovering it from the wrapped 'SyntaxError' when this is a 'Syntax'
+    // error instead of carrying one of its own.
+    pub fn position(&self) -> Position
+    {
+        match *self
+        {
+        ExprParseError::Syntax(ref err) => Position::from(err.range()),
+        ExprParseError::BadPrefix(_, pos) => pos,
+        ExprParseError::EmptyField(_, pos) => pos,
+        ExprParseError::DivByZero(pos) => pos,
+        ExprParseError::MismatchedParens(pos) => pos,
+        }
+    }
 }
 
 impl Error for ExprParseError
@@ -24,8 +46,10 @@ impl Error for ExprParseError
         match *self
         {
         ExprParseError::Syntax(ref err) => err.description(),
-        ExprParseError::BadPrefix(_) => "unknown metric prefix",
-        ExprParseError::EmptyField(_) => "field is empty",
+        ExprParseError::BadPrefix(..) => "unknown metric prefix",
+        ExprParseError::EmptyField(..) => "field is empty",
+        ExprParseError::DivByZero(..) => "division by zero",
+        ExprParseError::MismatchedParens(..) => "mismatched parentheses in value expression",
         }
     }
 
@@ -48,12 +72,18 @@ impl Display for ExprParseError
         ExprParseError::Syntax(ref err) => {
             write!(f, "{}", err)
         },
-        ExprParseError::BadPrefix(ref ch) => {
+        ExprParseError::BadPrefix(ref ch, _) => {
             write!(f, "parse error: {}: \'{}\'", self.description(), ch)
         },
-        ExprParseError::EmptyField(ref field) => {
+        ExprParseError::EmptyField(ref field, _) => {
             write!(f, "parse error: {} {}", field, self.description())
         },
+        ExprParseError::DivByZero(_) => {
+            write!(f, "parse error: {}", self.description())
+        },
+        ExprParseError::MismatchedParens(_) => {
+            write!(f, "parse error: {}", self.description())
+        },
         }
     }
 }
@@ -94,6 +124,71 @@ impl Display for GeneralParseError
     }
 }
 
+// Every 'GeneralParseError' hit while walking a line in 'to_conv_primitive',
+// rustc-diagnostics-vector style, so a REPL or batch-file front-end can list
+// every mistake in a line at once instead of making the user fix and re-run
+// one field at a time.
+#[derive(Debug)]
+pub struct MultiParseError
+{
+    pub errors: Vec<GeneralParseError>,
+}
+
+impl Error for MultiParseError
+{
+    fn description(&self) -> &str
+    {
+        "one or more fields in the conversion line failed to parse"
+    }
+
+    fn cause(&self) -> Option<&Error>
+    {
+        None
+    }
+}
+
+impl Display for MultiParseError
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+    {
+        for (i, err) in self.errors.iter().enumerate()
+        {
+            if i > 0
+            {
+                try!(write!(f, "\n"));
+            }
+
+            try!(write!(f, "argument {}: {}", err.failed_at, err.err));
+        }
+
+        Ok(())
+    }
+}
+
+// Renders 'line' - the original argument text at 'err.failed_at' - with a
+// caret line underlining the exact span 'err' points at, the same
+// presentation 'SyntaxError::render_carets' gives a tokenizer failure.
+// Lets a batch-file front-end show "argument 3 broke, and here's where"
+// instead of just the argument index.
+pub fn render_general_parse_error(err: &GeneralParseError, line: &str) -> String
+{
+    let pos = err.err.position();
+    let start = pos.offset.min(line.chars().count());
+    let end = (pos.offset + pos.len).max(start + 1);
+    let mut carets = String::with_capacity(end);
+
+    for _ in 0..start
+    {
+        carets.push(' ');
+    }
+    for _ in start..end
+    {
+        carets.push('^');
+    }
+
+    format!("{}\n{}\n{}", err, line, carets)
+}
+
 pub struct ConvPrimitive
 {
     pub input_vals: Vec<NumberExpr>,
@@ -109,7 +204,7 @@ enum ConvPrimState
     GetMoreValueExpr, // get any additional value expressions
     GetInputExpr,  // get the input unit expression
     GetOutputExpr, // get the output unit expression
-    GetMoreOutput, // get any additional output expressions. currently not used
+    GetMoreOutput, // get any additional, comma-separated output expressions
 }
 
 /* Takes a line of input that has had its spaces removed as a Vec of TokenType
@@ -117,21 +212,35 @@ enum ConvPrimState
  * in the program. Acts as an intermediary to filter out syntax errors before
  * they reach the main conversion routines.
  *
+ * Unlike a single bad field aborting the whole line, a token that fails to
+ * parse for whatever role the state machine currently expects is recorded
+ * as a 'GeneralParseError' and the token is skipped, leaving the state
+ * machine in the same state so the next token gets a chance to fill that
+ * same role. The one exception is a leading value expression: running out
+ * of (or never having) value exprs isn't an error on its own, so both
+ * 'GetValueExpr' and 'GetMoreValueExpr' instead reuse the token against
+ * 'GetInputExpr', resyncing at the value/unit boundary rather than logging
+ * a diagnostic for a field that was always optional.
+ *
  * Paramters:
  *   tokens - line tokenized at spaces given as Vec<TokenType>
  *
  * Returns: Result<>
- *   Ok(ConvPrimitve) - the line converted to expressions
- *   Error(ExprParseError) - error if any occured
+ *   Ok(ConvPrimitve) - the line converted to expressions, only returned if
+ *                       every token parsed cleanly
+ *   Error(MultiParseError) - every 'GeneralParseError' hit while walking the
+ *                             line
  */
-pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, GeneralParseError>
+pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, MultiParseError>
 {
     let mut value_exprs: Vec<NumberExpr> = Vec::new(); //NumberExpr { value: 0.0, recall: false };
     let mut unit_in_expr = UnitExpr { prefix: NO_PREFIX,
                                       alias: None,
                                       recall: false,
-                                      tag: None };
+                                      tag: None,
+                                      pos: Position::new(0, 0) };
     let mut unit_out_exprs: Vec<UnitExpr> = Vec::new();
+    let mut errors: Vec<GeneralParseError> = Vec::new();
 
     let mut state = ConvPrimState::GetValueExpr;
 
@@ -139,7 +248,7 @@ pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, G
     {
         let expr = match token
         {
-            &TokenType::Delim(_) =>
+            &TokenType::Delim(..) =>
             {
                 unreachable!("conversion primitive generator was given unsanitary input. delimiter detected");
             },
@@ -160,10 +269,13 @@ pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, G
                             state = ConvPrimState::GetMoreValueExpr;
                             reuse_token = false;
                         },
-                        Err(expr_parse_err) => {
-                            return Err(GeneralParseError { err: expr_parse_err,
-                                failed_at: index });
-                        }
+                        // not an error: a line with no value expressions at
+                        // all just means this token is the input unit expr
+                        // instead, same as running out of them in
+                        // 'GetMoreValueExpr'. Resync at the value/unit
+                        // boundary instead of getting stuck here and logging
+                        // a spurious error for every remaining token.
+                        Err(_expr_parse_err) => state = ConvPrimState::GetInputExpr,
                     };
                 },
                 ConvPrimState::GetMoreValueExpr => {
@@ -173,40 +285,45 @@ pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, G
                                 value_exprs.push(new_value_expr);
                                 reuse_token = false;
                             },
+                            // not an error: running out of value exprs just means
+                            // this token is the input unit expr instead
                             Err(expr_parse_err) => state = ConvPrimState::GetInputExpr,
                         };
                 },
                 ConvPrimState::GetInputExpr => {
-                    unit_in_expr = match parse_unit_expr(expr)
+                    match parse_unit_expr(expr)
                     {
-                        Ok(new_unit_expr) => new_unit_expr,
+                        Ok(new_unit_expr) => {
+                            unit_in_expr = new_unit_expr;
+                            state = ConvPrimState::GetOutputExpr;
+                        },
                         Err(parse_err) => {
-                            return Err(GeneralParseError { err: parse_err,
+                            errors.push(GeneralParseError { err: parse_err,
                                 failed_at: index });
                         }
                     };
-
-                    state = ConvPrimState::GetOutputExpr;
                     reuse_token = false;
                 },
                 ConvPrimState::GetOutputExpr => {
-                    match parse_unit_expr(expr)
+                    match parse_unit_expr_list(expr)
                     {
-                        Ok(new_unit_expr) => unit_out_exprs.push(new_unit_expr),
+                        Ok(mut new_unit_exprs) => {
+                            unit_out_exprs.append(&mut new_unit_exprs);
+                            state = ConvPrimState::GetMoreOutput;
+                        },
                         Err(parse_err) => {
-                            return Err(GeneralParseError { err: parse_err,
+                            errors.push(GeneralParseError { err: parse_err,
                                 failed_at: index });
                         }
                     };
-                    state = ConvPrimState::GetMoreOutput;
                     reuse_token = false;
                 },
                 ConvPrimState::GetMoreOutput => {
-                    match parse_unit_expr(expr)
+                    match parse_unit_expr_list(expr)
                     {
-                        Ok(new_unit_expr) => unit_out_exprs.push(new_unit_expr),
+                        Ok(mut new_unit_exprs) => unit_out_exprs.append(&mut new_unit_exprs),
                         Err(parse_err) => {
-                            return Err(GeneralParseError { err: parse_err,
+                            errors.push(GeneralParseError { err: parse_err,
                                 failed_at: index });
                         }
                     };
@@ -216,6 +333,11 @@ pub fn to_conv_primitive(mut tokens: &Vec<TokenType>) -> Result<ConvPrimitive, G
         }
     }
 
+    if !errors.is_empty()
+    {
+        return Err(MultiParseError { errors: errors });
+    }
+
     Ok(ConvPrimitive { input_vals: value_exprs,
                        input_unit: unit_in_expr,
                        output_units: unit_out_exprs })
@@ -1,157 +1,214 @@
 
 use ::runtime::parse::ExprParseError;
-use ::utils::*;
+use ::utils::{Position, SyntaxError};
 
-enum NumberCheckState
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExprOp
 {
-    FloatLiteral,
-    Semicolon,
-    Trailing,
+    Add,
+    Sub,
+    Mul,
+    Div,
 }
 
-struct NumberCheck<'a>
+impl ExprOp
 {
-    token: &'a String,
-    valid: bool,
-    state: NumberCheckState,
+    // '*' and '/' bind tighter than '+' and '-'. Equal precedence is popped
+    // off the operator stack before pushing, so all four operators end up
+    // left-associative.
+    fn precedence(&self) -> u8
+    {
+        match *self
+        {
+        ExprOp::Add | ExprOp::Sub => 1,
+        ExprOp::Mul | ExprOp::Div => 2,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ExprTok
+{
+    Number(f64, Position),
+    Op(ExprOp, Position),
+    LParen(Position),
+    RParen(Position),
 }
 
-impl<'a> NumberCheck<'a>
+// Splits a value expression into numeric literals, the four arithmetic
+// operators, and parentheses. A numeric literal is the maximal run of
+// digits and '.'; there is no unary +/- here, so a leading sign must be
+// written as '0-1' or similar.
+fn lex_expr(text: &str) -> Result<Vec<ExprTok>, ExprParseError>
 {
-    fn new(tok: &'a String) -> NumberCheck
+    let chars: Vec<char> = text.chars().collect();
+    let mut toks = Vec::with_capacity(chars.len());
+    let mut i = 0;
+
+    while i < chars.len()
     {
-        NumberCheck {
-            token: tok,
-            valid: true,
-            state: NumberCheckState::FloatLiteral,
-        }
+        match chars[i]
+        {
+        '+' => { toks.push(ExprTok::Op(ExprOp::Add, Position::new(i, 1))); i += 1; },
+        '-' => { toks.push(ExprTok::Op(ExprOp::Sub, Position::new(i, 1))); i += 1; },
+        '*' => { toks.push(ExprTok::Op(ExprOp::Mul, Position::new(i, 1))); i += 1; },
+        '/' => { toks.push(ExprTok::Op(ExprOp::Div, Position::new(i, 1))); i += 1; },
+        '(' => { toks.push(ExprTok::LParen(Position::new(i, 1))); i += 1; },
+        ')' => { toks.push(ExprTok::RParen(Position::new(i, 1))); i += 1; },
+        ch if ch.is_ascii_digit() || ch == '.' => {
+            let start = i;
+
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.')
+            {
+                i += 1;
+            }
+
+            let literal: String = chars[start..i].iter().collect();
+
+            let num = match literal.parse::<f64>()
+            {
+                Ok(num) => num,
+                Err(_) => return Err(ExprParseError::from(
+                        SyntaxError::Expected(start..i, "float literal".to_string()))),
+            };
+
+            toks.push(ExprTok::Number(num, Position::new(start, i - start)));
+        },
+        _ => return Err(ExprParseError::from(
+                SyntaxError::Expected(i..(i+1), "float literal, operator, or parenthesis".to_string()))),
+        };
     }
+
+    Ok(toks)
 }
 
-impl<'a> SyntaxChecker for NumberCheck<'a>
+// Rewrites a token stream from 'lex_expr' into reverse polish notation with
+// the shunting-yard algorithm: numbers go straight to the output queue,
+// operators are held on a stack and flushed to the output whenever an
+// operator of equal or higher precedence sits on top of it, and a ')'
+// flushes the stack back to its matching '('.
+fn to_rpn(toks: Vec<ExprTok>) -> Result<Vec<ExprTok>, ExprParseError>
 {
-    fn feed_token(&mut self, token: &str, delim: bool, index: usize) -> bool
+    let mut output: Vec<ExprTok> = Vec::with_capacity(toks.len());
+    let mut op_stack: Vec<ExprTok> = Vec::new();
+
+    for tok in toks
     {
-        if self.valid
+        match tok
         {
-            match self.state
+        ExprTok::Number(..) => output.push(tok),
+        ExprTok::Op(op, _) => {
+            while let Some(&ExprTok::Op(top_op, _)) = op_stack.last()
             {
-            NumberCheckState::FloatLiteral if !delim => {
-                if token.is_empty()
-                {
-                    self.state = NumberCheckState::Semicolon;
-                }
-                else if token.parse::<f64>().is_ok()
+                if top_op.precedence() >= op.precedence()
                 {
-                    self.state = NumberCheckState::Trailing;
+                    output.push(op_stack.pop().unwrap());
                 }
                 else
                 {
-                    self.valid = false;
+                    break;
                 }
-            },
-            NumberCheckState::Semicolon if delim => {
-                if token == ";"
-                {
-                    self.state = NumberCheckState::Trailing;
-                }
-                else
-                {
-                    self.valid = false;
-                }
-            },
+            }
 
-            NumberCheckState::Trailing => {
-                if !token.is_empty()
+            op_stack.push(tok);
+        },
+        ExprTok::LParen(_) => op_stack.push(tok),
+        ExprTok::RParen(close_pos) => {
+            loop
+            {
+                match op_stack.pop()
                 {
-                    self.valid = false;
-                }
-            },
-            _ => unreachable!("number syntax check reached impossible state"),
-            };
-        }
-
-        self.valid
-    }
-
-    fn is_esc(&self, ch: char) -> bool
-    {
-        false // no escape sequences allowed for numbers
-    }
-
-    fn is_comment(&self, ch: char) -> bool
-    {
-        ch == '#'
-    }
-
-    fn is_delim(&self, ch: char) -> bool
-    {
-        ch == ';'
+                Some(ExprTok::LParen(_)) => break,
+                Some(other) => output.push(other),
+                None => return Err(ExprParseError::MismatchedParens(close_pos)),
+                };
+            }
+        },
+        };
     }
 
-    fn is_preserved_delim(&self, ch: char) -> bool
+    while let Some(tok) = op_stack.pop()
     {
-        false
+        match tok
+        {
+        ExprTok::LParen(open_pos) => return Err(ExprParseError::MismatchedParens(open_pos)),
+        other => output.push(other),
+        };
     }
 
-    fn esc_char(&self) -> char
-    {
-        '\\' // dummy. actually no esc sequence.
-    }
+    Ok(output)
+}
 
-    fn valid(&self) -> bool
-    {
-        self.valid
-    }
+// Folds a reverse-polish token stream from 'to_rpn' down to a single value,
+// maintaining a value stack and popping two operands for every operator
+// encountered.
+fn eval_rpn(rpn: Vec<ExprTok>, whole_range: Position) -> Result<f64, ExprParseError>
+{
+    let mut stack: Vec<f64> = Vec::with_capacity(rpn.len());
 
-    fn assert_valid(&self, index: usize, more_tokens: bool) -> Result<(), SyntaxError>
+    for tok in rpn
     {
-        if !more_tokens || !self.valid
+        match tok
         {
-            match self.state
+        ExprTok::Number(num, _) => stack.push(num),
+        ExprTok::Op(op, pos) => {
+            let rhs = match stack.pop()
             {
-            NumberCheckState::FloatLiteral => {
-                // reached when receiving a non
-                return Err(SyntaxError::Expected(index, "float literal".to_string()));
-            },
-            NumberCheckState::Semicolon => {
-                // not okay to exit without receiving a recall expression
-                // not okay to exit without receiving anything
-                return Err(SyntaxError::Expected(index, "float literal or recall expression".to_string()));
-            },
-            _ => (),
+                Some(val) => val,
+                None => return Err(ExprParseError::from(
+                        SyntaxError::Expected(pos.range(), "operand before operator".to_string()))),
             };
-        }
-
-        if !self.valid
-        {
-            match self.state
+            let lhs = match stack.pop()
             {
-            NumberCheckState::Trailing => {
-                return Err(SyntaxError::Expected(index, "nothing after value expression".to_string()));
-            },
-            _ => (),
+                Some(val) => val,
+                None => return Err(ExprParseError::from(
+                        SyntaxError::Expected(pos.range(), "operand before operator".to_string()))),
             };
-        }
 
-        Ok(())
-    }
+            stack.push(match op
+            {
+            ExprOp::Add => lhs + rhs,
+            ExprOp::Sub => lhs - rhs,
+            ExprOp::Mul => lhs * rhs,
+            ExprOp::Div => {
+                if rhs == 0.0
+                {
+                    return Err(ExprParseError::DivByZero(pos));
+                }
 
-    fn esc_set(&self) -> bool
-    {
-        false
+                lhs / rhs
+            },
+            });
+        },
+        _ => unreachable!("parenthesis token survived shunting-yard pass"),
+        };
     }
 
-    fn set_esc(&mut self, set: bool)
+    match stack.len()
     {
-
+    1 => Ok(stack.pop().unwrap()),
+    _ => Err(ExprParseError::from(
+            SyntaxError::Expected(whole_range.range(), "well-formed value expression".to_string()))),
     }
+}
+
+// Evaluates a value-position expression such as '3*2+1' or '(5+1)/2' down to
+// a single f64. See 'to_rpn' for the shunting-yard pass and 'eval_rpn' for
+// folding the resulting RPN.
+fn eval_number_expr(text: &str) -> Result<f64, ExprParseError>
+{
+    let toks = try!(lex_expr(text));
 
-    fn reset(&mut self)
+    if toks.is_empty()
     {
-        self.valid = true;
-        self.state = NumberCheckState::FloatLiteral;
+        return Err(ExprParseError::from(
+                SyntaxError::Expected(0..1, "float literal".to_string())));
     }
+
+    let whole_range = Position::new(0, text.len());
+    let rpn = try!(to_rpn(toks));
+
+    eval_rpn(rpn, whole_range)
 }
 
 pub struct NumberExpr
@@ -162,53 +219,23 @@ pub struct NumberExpr
 
 pub fn parse_number_expr(token: &String) -> Result<NumberExpr, ExprParseError>
 {
-    let mut number_check = NumberCheck::new(token);
-    // if the syntax check passed, you know you are either getting a semicolon or a float literal
-    let mut tokens: Vec<TokenType> = try!(tokenize(token, &mut number_check));
-    tokens.retain(|tok| !tok.is_empty());
-
-    if tokens.len() < 1
+    // a trailing comment is stripped before the arithmetic pass reaches it,
+    // the same as the rest of the argument grammar treats '#'
+    let text = match token.find('#')
     {
-        return Err(
-            ExprParseError::from(
-                SyntaxError::Expected(0, "float literal or recall expression".to_string())));
-    }
-
-    let mut value_expr = NumberExpr {
-        value: -1.0,
-        recall: false,
+        Some(idx) => &token[..idx],
+        None => token.as_str(),
     };
 
-    for (index, tok) in tokens.drain(..).enumerate()
+    // the leading-recall syntax is detected before the arithmetic pass so
+    // that ';' alone keeps meaning "recall the last result" rather than
+    // being rejected as an unrecognized character
+    if text == ";"
     {
-        if index > 0
-        {
-            unreachable!("too many tokens in value expression after syntax check");
-        }
-
-        match tok
-        {
-        TokenType::Normal(number) => {
-            value_expr.value = match number.parse::<f64>()
-            {
-            Ok(num) => num,
-            Err(err) => {
-                unreachable!("float literal cannot be parsed as such after syntax check");
-            },
-            };
-        },
-        TokenType::Delim(delim) => {
-            if delim == ";"
-            {
-                value_expr.recall = true;
-            }
-            else
-            {
-                unreachable!("illegal value recall character after syntax check");
-            }
-        },
-        };
+        return Ok(NumberExpr { value: 0.0, recall: true });
     }
 
-    Ok(value_expr)
-}
\ No newline at end of file
+    let value = try!(eval_number_expr(text));
+
+    Ok(NumberExpr { value: value, recall: false })
+}
@@ -1,128 +1,36 @@
 
-use std::vec::Drain;
+use std::ops::Range;
+use std::rc::Rc;
 
 use ::utils::*;
 use ::runtime::parse::ExprParseError;
-
-enum UnitCheckState
-{
-    NameOrExpr,
-    UnderscoreOrColon,
-    PrefixOrName,
-    Colon,
-    FinishOrTag,
-    Tag,
-    Finish
-}
-
-
-struct UnitCheck
+use ::runtime::parse::intern::intern;
+use ::runtime::parse::combinator::{Parser, alt, any_token, many, map, opt, satisfies, seq};
+
+// Lexes a unit expression into tokens on '_', ':' and '@'. Unlike the state
+// machine it replaces ('UnitCheck'), this checker enforces none of the
+// expression's grammar itself - 'feed_token'/'assert_valid' are no-ops. The
+// grammar now lives entirely in the combinator parser below, which both
+// validates and builds the 'UnitExpr' in one traversal of the token stream,
+// so there is no separate error-mapping table to keep in sync with it.
+struct UnitLex
 {
     esc_seq: bool,
-    valid: bool,
-    state: UnitCheckState,
 }
 
-impl UnitCheck
+impl UnitLex
 {
-    fn new() -> UnitCheck
+    fn new() -> UnitLex
     {
-        UnitCheck {
-            esc_seq: false,
-            valid: true,
-            state: UnitCheckState::NameOrExpr,
-        }
+        UnitLex { esc_seq: false }
     }
 }
 
-impl SyntaxChecker for UnitCheck
+impl SyntaxChecker for UnitLex
 {
-    fn feed_token(&mut self, token: &str, delim: bool, index: usize) -> bool
+    fn feed_token(&mut self, _token: &str, _delim: bool, _range: Range<usize>) -> bool
     {
-        if self.valid
-        {
-            match self.state
-            {
-            UnitCheckState::NameOrExpr if !delim => {
-                if token.is_empty()
-                {
-                    self.state = UnitCheckState::UnderscoreOrColon;
-                }
-                else
-                {
-                    self.state = UnitCheckState::FinishOrTag;
-                }
-            },
-            UnitCheckState::UnderscoreOrColon if delim => {
-                if token == "_"
-                {
-                    self.state = UnitCheckState::PrefixOrName;
-                }
-                else if token == ":"
-                {
-                    self.state = UnitCheckState::FinishOrTag;
-                }
-                else
-                {
-                    self.valid = false;
-                }
-            },
-            UnitCheckState::PrefixOrName if !delim => {
-                if token.is_empty()
-                {
-                    self.valid = false;
-                }
-                else if token.len() < 2
-                {
-                    self.state = UnitCheckState::Colon;
-                }
-                else
-                {
-                    self.state = UnitCheckState::FinishOrTag;
-                }
-            },
-            UnitCheckState::Colon if delim => {
-                if token == ":"
-                {
-                    self.state = UnitCheckState::FinishOrTag;
-                }
-                else
-                {
-                    self.valid = false;
-                }
-            },
-            UnitCheckState::FinishOrTag => {
-                if token == "@"
-                {
-                    self.state = UnitCheckState::Tag
-                }
-                else if !token.is_empty()
-                {
-                    self.valid = false;
-                }
-                // if token is empty, it means we came from Colon. Wait for next
-            },
-            UnitCheckState::Tag if !delim => {
-                if token.is_empty()
-                {
-                    self.valid = false;
-                }
-                else
-                {
-                    self.state = UnitCheckState::Finish;
-                }
-            },
-            UnitCheckState::Finish => {
-                if !token.is_empty()
-                {
-                    self.valid = false;
-                }
-            },
-            _ => unreachable!("unit expression syntax check reached impossible state"),
-            };
-        }
-
-        self.valid
+        true
     }
 
     fn is_esc(&self, ch: char) -> bool
@@ -130,7 +38,7 @@ impl SyntaxChecker for UnitCheck
         ch == '\\'
     }
 
-    fn is_comment(&self, ch: char) -> bool
+    fn is_comment(&self, _ch: char) -> bool
     {
         false
     }
@@ -142,7 +50,7 @@ impl SyntaxChecker for UnitCheck
         ch == '@'
     }
 
-    fn is_preserved_delim(&self, ch: char) -> bool
+    fn is_preserved_delim(&self, _ch: char) -> bool
     {
         false
     }
@@ -154,47 +62,11 @@ impl SyntaxChecker for UnitCheck
 
     fn valid(&self) -> bool
     {
-        self.valid
+        true
     }
 
-    fn assert_valid(&self, index: usize, more_tokens: bool) -> Result<(), SyntaxError>
+    fn assert_valid(&self, _range: Range<usize>, _more_tokens: bool) -> Result<(), SyntaxError>
     {
-        if !more_tokens || !self.valid
-        {
-            match self.state
-            {
-            UnitCheckState::NameOrExpr | UnitCheckState::UnderscoreOrColon => {
-                return Err(SyntaxError::Expected(index,
-                        "unit name or recall expression".to_string()));
-            },
-            UnitCheckState::PrefixOrName | UnitCheckState::Colon => {
-                return Err(SyntaxError::Expected(index,
-                        "metric prefix together with unit name / recall expression".to_string()));
-            },
-            UnitCheckState::Tag => {
-                return Err(SyntaxError::Expected(index,
-                        "a non-emtpy tag for the unit".to_string()));
-            }
-            _ => (),
-            };
-        }
-
-        if !self.valid
-        {
-            match self.state
-            {
-            UnitCheckState::FinishOrTag => {
-                return Err(SyntaxError::Expected(index,
-                        "a tag or nothing at all after unit name / recall expression".to_string()));
-            },
-            UnitCheckState::Finish => {
-                return Err(SyntaxError::Expected(index,
-                        "nothing following a tag".to_string()));
-            }
-            _ => (),
-            };
-        }
-
         Ok(())
     }
 
@@ -210,8 +82,6 @@ impl SyntaxChecker for UnitCheck
 
     fn reset(&mut self)
     {
-        self.valid = true;
-        self.state = UnitCheckState::NameOrExpr;
         self.esc_seq = false;
     }
 }
@@ -220,105 +90,154 @@ impl SyntaxChecker for UnitCheck
 pub struct UnitExpr
 {
     pub prefix: char,
-    pub alias: Option<String>,
+    // interned via 'intern::intern' so that identical aliases across a
+    // batch of conversions share one allocation and 'UnitExpr' stays cheap
+    // to clone.
+    pub alias: Option<Rc<str>>,
     pub recall: bool,
-    pub tag: Option<String>,
+    pub tag: Option<Rc<str>>,
+    // character span of the whole expression in its original field, for pointing
+    // a caret at the right place when a later stage (ex. database lookup)
+    // rejects an otherwise syntactically valid unit expression.
+    pub pos: Position,
+}
+
+// Matches a single 'Delim' token whose text is exactly 'text'.
+fn delim<'t>(text: &'static str, expected: &'static str) -> Parser<'t, TokenType>
+{
+    satisfies(expected, move |tok: &TokenType| match *tok
+    {
+        TokenType::Delim(ref found, _) => found == text,
+        _ => false,
+    })
 }
 
-fn process_alias_or_recall(next_token: Option<TokenType>, unit_expr: &mut UnitExpr, tokens_iter: &mut Drain<TokenType>)
-    -> Result<Option<TokenType>, ExprParseError>
+// Matches a non-empty 'Normal' token, whatever its length.
+fn alias_token<'t>(expected: &'static str) -> Parser<'t, TokenType>
 {
-    match next_token.unwrap()
+    satisfies(expected, |tok: &TokenType| match *tok
     {
-        TokenType::Normal(alias) => unit_expr.alias = Some(alias),
-        TokenType::Delim(ref delim) if delim == ":" => unit_expr.recall = true,
-        token @ _ => unreachable!("unexpected token while parsing alias / recall: {:?}", token),
-    };
+        TokenType::Normal(ref text, _) => !text.is_empty(),
+        _ => false,
+    })
+}
+
+// Matches a 'Normal' token of 2 or more chars, the combined "<prefix><name>"
+// form ("kg" = kilo + "g"). Splits it into the prefix char and the interned
+// remainder.
+fn prefixed_name<'t>() -> Parser<'t, (char, Rc<str>)>
+{
+    map(satisfies("a metric prefix directly followed by a unit name, e.g. \'kg\'",
+            |tok: &TokenType| match *tok
+            {
+                TokenType::Normal(ref text, _) => text.chars().count() >= 2,
+                _ => false,
+            }),
+        |tok| {
+            let text = tok.unwrap();
+            let prefix = text.chars().next().unwrap();
+            let alias = intern(&text[prefix.len_utf8()..]);
+            (prefix, alias)
+        })
+}
+
+// Matches a lone metric prefix character with nothing else in its token -
+// only legal directly before a '_name:' recall, since a bare prefix letter
+// can't also be read as a one letter unit name.
+fn lone_prefix<'t>() -> Parser<'t, char>
+{
+    map(satisfies("a metric prefix together with a unit name / recall expression",
+            |tok: &TokenType| match *tok
+            {
+                TokenType::Normal(ref text, _) => text.chars().count() == 1,
+                _ => false,
+            }),
+        |tok| tok.unwrap().chars().next().unwrap())
+}
+
+// '_' followed by either a combined "<prefix><name>" token, or a lone
+// prefix letter followed by ':' (a recall of the prefixed unit).
+fn prefixed_form<'t>() -> Parser<'t, (char, Option<Rc<str>>, bool)>
+{
+    map(seq(delim("_", "a unit name or recall expression"),
+            alt(map(prefixed_name(), |(prefix, alias)| (prefix, Some(alias), false)),
+                map(seq(lone_prefix(), delim(":", "metric prefix together with unit name / recall expression")),
+                    |(prefix, _)| (prefix, None, true)))),
+        |(_, body)| body)
+}
 
-    Ok(tokens_iter.next())
+// A bare alias, or a bare ':' recall, neither carrying a metric prefix.
+fn unprefixed_form<'t>() -> Parser<'t, (char, Option<Rc<str>>, bool)>
+{
+    alt(map(delim(":", "unit name or recall expression"),
+            |_| (NO_PREFIX, None, true)),
+        map(alias_token("unit name or recall expression"),
+            |tok| (NO_PREFIX, Some(intern(&tok.unwrap())), false)))
 }
 
-fn process_tag(next_token: Option<TokenType>, unit_expr: &mut UnitExpr, tokens_iter: &mut Drain<TokenType>)
-    -> Result<Option<TokenType>, ExprParseError>
+// '@' followed by a non-empty tag name.
+fn tag_form<'t>() -> Parser<'t, Rc<str>>
 {
-    let more = if next_token.is_some()
+    map(seq(delim("@", "a tag after \'@\'"), alias_token("a non-emtpy tag for the unit")),
+        |(_, tok)| intern(&tok.unwrap()))
+}
+
+// Splits an output-unit argument on ',' so "ft,in,cm" parses to one
+// 'UnitExpr' per listed target instead of requiring a separate argument for
+// each; a single unit with no comma still comes back as a one-element
+// vec, so callers don't need a special case for the common single-target
+// line. Each part's 'pos' is shifted by its offset in 'token' so a caret
+// still lands under the right comma-separated piece rather than always
+// under the first.
+pub fn parse_unit_expr_list(token: &String) -> Result<Vec<UnitExpr>, ExprParseError>
+{
+    let mut exprs = Vec::with_capacity(1);
+    let mut offset = 0;
+
+    for part in token.split(',')
     {
-        match next_token.unwrap()
-        {
-            TokenType::Delim(ref delim) if delim == "@" => unit_expr.tag = Some(tokens_iter.next().unwrap().unwrap()),
-            token @ _ => unreachable!("unexpected token while parsing tag: {:?}", token),
-        };
-        tokens_iter.next()
+        let mut expr = try!(parse_unit_expr(&part.to_string()));
+        expr.pos = Position::from((expr.pos.range().start + offset)..(expr.pos.range().end + offset));
+        offset += part.chars().count() + 1; // +1 for the ',' consumed by 'split'
+        exprs.push(expr);
     }
-    else
-    {
-        None
-    };
 
-    Ok(more)
+    Ok(exprs)
 }
 
 pub fn parse_unit_expr(token: &String) -> Result<UnitExpr, ExprParseError>
 {
-    let mut expr_checker = UnitCheck::new();
-    let mut tokens: Vec<TokenType> = try!(tokenize(token, &mut expr_checker));
+    let mut lexer = UnitLex::new();
+    let mut tokens: Vec<TokenType> = try!(tokenize(token, &mut lexer));
     tokens.retain(|tok| !tok.is_empty());
 
-    if tokens.len() < 1
+    if tokens.is_empty()
     {
-        return Err(ExprParseError::from(SyntaxError::Expected(0,
+        return Err(ExprParseError::from(SyntaxError::Expected(0..token.len(),
                 "metric prefix together with unit name / recall expression".to_string())));
     }
 
-    let mut unit_expr = UnitExpr {
-        prefix: NO_PREFIX,
-        alias: None,
-        recall: false,
-        tag: None,
-    };
+    let expr_pos = Position::from(tokens[0].position().offset..tokens[tokens.len()-1].position().range().end);
+    let end_pos = Position::from(expr_pos.range().end..expr_pos.range().end);
+
+    let (rest, (prefix, alias, recall)) = try!(alt(prefixed_form(), unprefixed_form())(&tokens, end_pos));
+
+    if prefix != NO_PREFIX && prefix_as_num(prefix).is_none()
+    {
+        return Err(ExprParseError::BadPrefix(prefix, expr_pos));
+    }
+
+    let (rest, tag) = try!(opt(tag_form())(rest, end_pos));
 
-    let mut tokens_iter = tokens.drain(..);
+    let (rest, trailing) = try!(many(any_token())(rest, end_pos));
 
-    match tokens_iter.next().unwrap()
+    if !trailing.is_empty()
     {
-    TokenType::Delim(ref delim) if delim == "_" => {
-        let mut alias = tokens_iter.next().unwrap().unwrap();
-        let mut new_alias = String::with_capacity(alias.len() - 1);
-        let mut alias_iter = alias.chars();
-        let prefix = alias_iter.next().unwrap();
-
-        if prefix_as_num(prefix).is_none()
-        {
-            return Err(ExprParseError::BadPrefix(prefix));
-        }
-
-        unit_expr.prefix = prefix;
-
-        for ch in alias_iter
-        {
-            new_alias.push(ch);
-        }
-
-        let mut iter_result = tokens_iter.next();
-
-        iter_result = try!(process_alias_or_recall(iter_result, &mut unit_expr, &mut tokens_iter));
-        iter_result = try!(process_tag(iter_result, &mut unit_expr, &mut tokens_iter));
-
-        if iter_result.is_some()
-        {
-            unreachable!("extra tokens in unit expression after syntax check");
-        }
-    },
-    TokenType::Delim(ref delim) if delim == ":" => {
-        unit_expr.recall = true;
-        let iter_result = try!(process_tag(tokens_iter.next(), &mut unit_expr, &mut tokens_iter));
-    },
-    TokenType::Normal(alias) => {
-        unit_expr.alias = Some(alias);
-        let iter_result = try!(process_tag(tokens_iter.next(), &mut unit_expr, &mut tokens_iter));
-    },
-    _ => unreachable!("unexpected token begins unit expression"),
-    };
-
-    Ok(unit_expr)
-}
\ No newline at end of file
+        return Err(ExprParseError::from(SyntaxError::Expected(trailing[0].position().range(),
+                "a tag or nothing at all after unit name / recall expression".to_string())));
+    }
+
+    debug_assert!(rest.is_empty());
+
+    Ok(UnitExpr { prefix: prefix, alias: alias, recall: recall, tag: tag, pos: expr_pos })
+}
@@ -1,11 +1,19 @@
-use runtime::convert::ConversionFmt;
-use runtime::InterpretErr;
 use std::env;
+use std::rc::Rc;
+
+use runtime::convert::ConversionFmt;
+use runtime::parse::number::parse_number_expr;
+use runtime::parse::unit::parse_unit_expr;
+use runtime::{InterpretErr, NONLITERAL_RECALL_MSG};
+use utils::NO_PREFIX;
 
 pub struct Options
 {
     interactive: bool,
     format: ConversionFmt,
+    preset_value: Option<f64>,
+    preset_input_unit: Option<Rc<str>>,
+    preset_output_unit: Option<Rc<str>>,
 }
 
 impl Options
@@ -15,9 +23,37 @@ impl Options
         Options {
             interactive: true,
             format: ConversionFmt::Desc,
+            preset_value: None,
+            preset_input_unit: None,
+            preset_output_unit: None,
         }
     }
 
+    pub fn interactive(&self) -> bool
+    {
+        self.interactive
+    }
+
+    pub fn format(&self) -> ConversionFmt
+    {
+        self.format
+    }
+
+    pub fn preset_value(&self) -> Option<f64>
+    {
+        self.preset_value
+    }
+
+    pub fn preset_input_unit(&self) -> Option<Rc<str>>
+    {
+        self.preset_input_unit.clone()
+    }
+
+    pub fn preset_output_unit(&self) -> Option<Rc<str>>
+    {
+        self.preset_output_unit.clone()
+    }
+
     pub fn get_opts() -> Result<(Options, Vec<String>), InterpretErr>
     {
         let mut opts = Options::new();
@@ -39,6 +75,7 @@ impl Options
                 {
                 "--help" => return Err(InterpretErr::HelpSig),
                 "--version" => return Err(InterpretErr::VersionSig),
+                "--csv" => opts.format = ConversionFmt::Csv,
                 _ => return Err(InterpretErr::UnknownLongOpt(arg)),
                 };
             }
@@ -65,12 +102,41 @@ impl Options
                 {
                     let mut chars = arg.chars();
                     chars.next(); // get rid of dash
-                    for ch in chars
+
+                    while let Some(ch) = chars.next()
                     {
                         match ch
                         {
                         's' => opts.format = ConversionFmt::Short,
                         'l' => opts.format = ConversionFmt::Long,
+                        'f' | 't' | 'v' => {
+                            // the rest of this token if non-empty (bundled,
+                            // ex. '-fkg'), else the next whole argument
+                            // (separate, ex. '-f kg') is the value
+                            let rest: String = chars.collect();
+                            let value = if rest.is_empty()
+                            {
+                                match args.next()
+                                {
+                                Some(value) => value,
+                                None => return Err(InterpretErr::IncompleteErr),
+                                }
+                            }
+                            else
+                            {
+                                rest
+                            };
+
+                            match ch
+                            {
+                            'f' => opts.preset_input_unit = Some(parse_preset_unit(&value)?),
+                            't' => opts.preset_output_unit = Some(parse_preset_unit(&value)?),
+                            'v' => opts.preset_value = Some(parse_preset_value(&value)?),
+                            _ => unreachable!(),
+                            };
+
+                            break; // any remaining chars were consumed as the value above
+                        },
                         _ => return Err(InterpretErr::UnknownShortOpt(ch)),
                         };
                     }
@@ -97,4 +163,42 @@ impl Options
 
         Ok((opts, extras))
     }
-}
\ No newline at end of file
+}
+
+// Parses 'raw' as a literal unit alias for an '-f'/'-t' preset, same
+// literal-only rule 'Interpreter::interpret' enforces for its
+// 'input_unit'/'output_unit' commands: no prefix and no recall, since there
+// is no prior conversion yet to recall from at CLI parse time.
+fn parse_preset_unit(raw: &str) -> Result<Rc<str>, InterpretErr>
+{
+    let expr = match parse_unit_expr(&raw.to_string())
+    {
+        Ok(expr) => expr,
+        Err(err) => return Err(InterpretErr::InvalidState(format!("{}", err))),
+    };
+
+    if expr.alias.is_none() || expr.prefix != NO_PREFIX || expr.recall
+    {
+        return Err(InterpretErr::InvalidState(NONLITERAL_RECALL_MSG.to_string()));
+    }
+
+    Ok(expr.alias.unwrap())
+}
+
+// Parses 'raw' as a literal value for a '-v' preset; same recall-free rule
+// as 'parse_preset_unit' above.
+fn parse_preset_value(raw: &str) -> Result<f64, InterpretErr>
+{
+    let expr = match parse_number_expr(&raw.to_string())
+    {
+        Ok(expr) => expr,
+        Err(err) => return Err(InterpretErr::InvalidState(format!("{}", err))),
+    };
+
+    if expr.recall
+    {
+        return Err(InterpretErr::InvalidState(NONLITERAL_RECALL_MSG.to_string()));
+    }
+
+    Ok(expr.value)
+}
@@ -21,8 +21,16 @@
 
 pub mod config;
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, BTreeSet};
+use std::error::Error;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use std::fs::File;
+use std::io;
+use std::io::{Read, Write};
+use std::mem;
 use std::rc::Rc;
+use std::str::FromStr;
 
 // unit types Yucon recognizes
 // statically allocated so that we do not waste memory storing duplicate data
@@ -95,6 +103,11 @@ pub struct UnitDatabase
     units: Vec<Rc<Unit>>,
     preferred_namespace: Rc<String>,
     //default_namespace_: Rc<String>
+    // path this database was loaded from via 'load_from_file', if any. Lets
+    // 'save_to_file' write back to the same 'units.yaml' without the caller
+    // having to remember and re-pass it, so a runtime 'define' can persist
+    // without plumbing the path through the interpreter too.
+    source_path: Option<String>,
 }
 
 impl UnitDatabase
@@ -111,7 +124,8 @@ impl UnitDatabase
                        namespaces: namespaces_,
                        units: Vec::new(),
                        preferred_namespace: preferred,
-                       /*default_namespace_: default,*/ }
+                       /*default_namespace_: default,*/
+                       source_path: None, }
     }
 
     /*
@@ -119,7 +133,10 @@ impl UnitDatabase
     tags / namespaces. If a collision is detected, the first namespace and alias
     that caused a collision are returned.
      */
-    fn check_collisions(&self,
+    // Not private: the 'define' command (see 'runtime::Interpreter::interpret')
+    // re-runs this after a rejected 'add' to name the specific alias/tag that
+    // collided, since 'add' itself only hands back the rejected 'Unit'.
+    pub(crate) fn check_collisions(&self,
                         unit: &Unit,
                         aliases: &Vec<Rc<String>>,
                         tags: &Vec<Rc<String>>) -> Option<(Rc<String>, Rc<String>)>
@@ -263,15 +280,16 @@ impl UnitDatabase
         None
     }
 
-    pub fn query(&self, name: &String, tag: Option<&String>) -> Option<Rc<Unit>>
+    pub fn query(&self, name: &str, tag: Option<&str>) -> Option<Rc<Unit>>
     {
         //println!("name: {:?}    tag: {:?}", name, tag);
+        let name_key = name.to_string();
         let unit_result = if tag.is_some()
         {
             // if the unit was tagged, search only in the tagged namespace
-            if let Some(namespace) = self.namespaces.get(tag.unwrap())
+            if let Some(namespace) = self.namespaces.get(&tag.unwrap().to_string())
             {
-                if let Some(unit_rc) = namespace.get(&Rc::new(name.clone()))
+                if let Some(unit_rc) = namespace.get(&Rc::new(name_key))
                 {
                     Some(unit_rc.clone())
                 }
@@ -291,7 +309,7 @@ impl UnitDatabase
             // 1. Preferred tag
             // 2. Default namespace
             // 3. All registered namespaces in alphabetical order
-            let mut inner_result = if let Some(unit) = self.namespaces.get(&self.preferred_namespace).unwrap().get(name)
+            let mut inner_result = if let Some(unit) = self.namespaces.get(&self.preferred_namespace).unwrap().get(&name_key)
             {
                 Some(unit.clone())
             }
@@ -302,7 +320,7 @@ impl UnitDatabase
 
             if inner_result.is_none()
             {
-                inner_result = if let Some(unit) = self.default_namespace.get(name)
+                inner_result = if let Some(unit) = self.default_namespace.get(&name_key)
                 {
                     Some(unit.clone())
                 }
@@ -320,7 +338,7 @@ impl UnitDatabase
                     {
                         continue;
                     }
-                    if let Some(unit) = namespace.get(name)
+                    if let Some(unit) = namespace.get(&name_key)
                     {
                         inner_result = Some(unit.clone());
                         break;
@@ -339,6 +357,452 @@ impl UnitDatabase
 
         unit_result
     }
+
+    // Collects every alias registered across all namespaces (the default
+    // namespace plus every tag, preferred or not), deduplicated and sorted.
+    // Meant for front-ends that want to offer completion over the whole
+    // database at once rather than one tag at a time.
+    pub fn all_aliases(&self) -> Vec<Rc<String>>
+    {
+        let mut aliases: BTreeSet<Rc<String>> = BTreeSet::new();
+
+        for alias in self.default_namespace.keys()
+        {
+            aliases.insert(alias.clone());
+        }
+
+        for namespace in self.namespaces.values()
+        {
+            for alias in namespace.keys()
+            {
+                aliases.insert(alias.clone());
+            }
+        }
+
+        aliases.into_iter().collect()
+    }
+
+    // Ranks every registered alias by closeness to 'term' and returns the
+    // 'limit' best matches. An alias containing 'term' as a substring is
+    // treated as an exact match (distance 0); everything else is ranked by
+    // Levenshtein edit distance against the lowercased alias, ties broken
+    // lexicographically. Meant to back the 'search' command and to suggest
+    // corrections when a conversion's unit isn't found.
+    pub fn search(&self, term: &str, limit: usize) -> Vec<(Rc<String>, Rc<Unit>)>
+    {
+        let term = term.to_lowercase();
+        let mut ranked: Vec<(usize, Rc<String>, Rc<Unit>)> = Vec::new();
+
+        let all_aliases = self.default_namespace.iter()
+            .chain(self.namespaces.values().flat_map(|namespace| namespace.iter()));
+
+        for (alias, unit) in all_aliases
+        {
+            let alias_lower = alias.to_lowercase();
+            let distance = if alias_lower.contains(&term)
+            {
+                0
+            }
+            else
+            {
+                levenshtein(&term, &alias_lower)
+            };
+
+            ranked.push((distance, alias.clone(), unit.clone()));
+        }
+
+        ranked.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+        ranked.truncate(limit);
+
+        ranked.into_iter().map(|(_, alias, unit)| (alias, unit)).collect()
+    }
+
+    // Lists every registered unit alongside every alias that resolves to it,
+    // across every namespace it was registered in, grouped by unit rather
+    // than flattened like 'all_aliases'. Meant to back the 'list' command.
+    pub fn list_units(&self) -> Vec<(Rc<Unit>, Vec<Rc<String>>)>
+    {
+        let mut grouped: Vec<(Rc<Unit>, Vec<Rc<String>>)> = Vec::with_capacity(self.units.len());
+
+        for unit in self.units.iter()
+        {
+            let mut aliases: BTreeSet<Rc<String>> = BTreeSet::new();
+
+            for (alias, candidate) in self.default_namespace.iter()
+            {
+                if Rc::ptr_eq(candidate, unit)
+                {
+                    aliases.insert(alias.clone());
+                }
+            }
+
+            for namespace in self.namespaces.values()
+            {
+                for (alias, candidate) in namespace.iter()
+                {
+                    if Rc::ptr_eq(candidate, unit)
+                    {
+                        aliases.insert(alias.clone());
+                    }
+                }
+            }
+
+            grouped.push((unit.clone(), aliases.into_iter().collect()));
+        }
+
+        grouped
+    }
+
+    // Like 'list_units', but also captures which tag(s) each unit is
+    // registered under (empty for a default-namespace unit) and excludes
+    // the unit's own 'common_name' from its aliases, so 'save_to_file' can
+    // round-trip a unit through 'UnitRecord' without duplicating the name.
+    fn list_units_with_tags(&self) -> Vec<(Rc<Unit>, Vec<Rc<String>>, Vec<Rc<String>>)>
+    {
+        let mut grouped: Vec<(Rc<Unit>, Vec<Rc<String>>, Vec<Rc<String>>)> = Vec::with_capacity(self.units.len());
+
+        for unit in self.units.iter()
+        {
+            let mut aliases: BTreeSet<Rc<String>> = BTreeSet::new();
+            let mut tags: BTreeSet<Rc<String>> = BTreeSet::new();
+
+            for (alias, candidate) in self.default_namespace.iter()
+            {
+                if Rc::ptr_eq(candidate, unit) && *alias != unit.common_name
+                {
+                    aliases.insert(alias.clone());
+                }
+            }
+
+            for (tag, namespace) in self.namespaces.iter()
+            {
+                for (alias, candidate) in namespace.iter()
+                {
+                    if Rc::ptr_eq(candidate, unit)
+                    {
+                        tags.insert(tag.clone());
+
+                        if *alias != unit.common_name
+                        {
+                            aliases.insert(alias.clone());
+                        }
+                    }
+                }
+            }
+
+            grouped.push((unit.clone(), aliases.into_iter().collect(), tags.into_iter().collect()));
+        }
+
+        grouped
+    }
+
+    // Loads a units database from the 'units.yaml' schema 'save_to_file'
+    // writes: a flat list of unit records rather than the namespace/alias
+    // maps 'UnitDatabase' actually indexes by, same division of labor as
+    // 'config::load_units_list' uses for the legacy 'units.cfg' format.
+    // Remembers 'path' so a later 'save_to_file' (ex. from the 'define'
+    // command) writes back to the same file.
+    pub fn load_from_file(path: &str, preferred_namespace: Option<String>) -> Result<UnitDatabase, UnitDbError>
+    {
+        let mut file = File::open(path)?;
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        let parsed: UnitsFile = ::serde_yaml::from_str(&contents)?;
+
+        let preferred = Rc::new(preferred_namespace.unwrap_or_else(|| "us".to_string()));
+        let mut namespaces = BTreeMap::new();
+        namespaces.insert(preferred.clone(), BTreeMap::new());
+
+        let mut db = UnitDatabase { default_namespace: BTreeMap::new(),
+                                    namespaces: namespaces,
+                                    units: Vec::new(),
+                                    preferred_namespace: preferred,
+                                    source_path: Some(path.to_string()), };
+
+        for record in parsed.units
+        {
+            let (unit, aliases, tags) = record.into_unit()?;
+            db.add(unit, &aliases, &tags);
+        }
+
+        Ok(db)
+    }
+
+    // Serializes every registered unit back out through the same
+    // 'UnitRecord'/'UnitsFile' schema 'load_from_file' reads, to whichever
+    // path this database was loaded from. Meant to let the 'define' command
+    // persist a unit added at runtime so it survives the next restart.
+    pub fn save_to_file(&self) -> Result<(), UnitDbError>
+    {
+        let path = match self.source_path
+        {
+            Some(ref path) => path,
+            None => return Err(UnitDbError::NoSourcePath),
+        };
+
+        let units = self.list_units_with_tags()
+            .into_iter()
+            .map(|(unit, aliases, tags)| UnitRecord::from_unit(&unit, aliases, tags))
+            .collect();
+
+        let yaml = ::serde_yaml::to_string(&UnitsFile { units: units })?;
+
+        let mut file = File::create(path)?;
+        file.write_all(yaml.as_bytes())?;
+
+        Ok(())
+    }
+}
+
+// On-disk shape of one unit in 'units.yaml': plain owned fields rather than
+// 'Unit's interned/'&'static str' ones, since those can't round-trip through
+// serde on their own ('unit_type' is resolved against 'UNIT_TYPES' by name
+// instead of being deserialized directly).
+#[derive(Serialize, Deserialize, Debug)]
+struct UnitRecord
+{
+    common_name: String,
+    unit_type: String,
+    conv_factor: f64,
+    #[serde(default = "default_dimensions")]
+    dimensions: u8,
+    #[serde(default)]
+    zero_point: f64,
+    #[serde(default)]
+    inverse: bool,
+    #[serde(default)]
+    aliases: Vec<String>,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+fn default_dimensions() -> u8 { 1 }
+
+#[derive(Serialize, Deserialize, Debug)]
+struct UnitsFile
+{
+    units: Vec<UnitRecord>,
+}
+
+impl UnitRecord
+{
+    fn from_unit(unit: &Unit, aliases: Vec<Rc<String>>, tags: Vec<Rc<String>>) -> UnitRecord
+    {
+        UnitRecord { common_name: (*unit.common_name).clone(),
+                     unit_type: unit.unit_type.to_string(),
+                     conv_factor: unit.conv_factor,
+                     dimensions: unit.dimensions,
+                     zero_point: unit.zero_point,
+                     inverse: unit.inverse,
+                     aliases: aliases.iter().map(|alias| (**alias).clone()).collect(),
+                     tags: tags.iter().map(|tag| (**tag).clone()).collect(), }
+    }
+
+    fn into_unit(self) -> Result<(Unit, Vec<Rc<String>>, Vec<Rc<String>>), UnitDbError>
+    {
+        let unit_type = UNIT_TYPES.iter()
+            .find(|candidate| candidate.eq_ignore_ascii_case(&self.unit_type))
+            .ok_or_else(|| UnitDbError::UnknownUnitType(self.unit_type.clone()))?;
+
+        let aliases: Vec<Rc<String>> = self.aliases.into_iter().map(Rc::new).collect();
+        let tags: Vec<Rc<String>> = self.tags.into_iter().map(Rc::new).collect();
+
+        let unit = Unit { common_name: Rc::new(self.common_name),
+                           conv_factor: self.conv_factor,
+                           dimensions: self.dimensions,
+                           inverse: self.inverse,
+                           unit_type: unit_type,
+                           zero_point: self.zero_point,
+                           has_aliases: !aliases.is_empty(),
+                           has_tags: !tags.is_empty(), };
+
+        Ok((unit, aliases, tags))
+    }
+}
+
+#[derive(Debug)]
+pub enum UnitDbError
+{
+    Io(io::Error),
+    Yaml(::serde_yaml::Error),
+    UnknownUnitType(String),
+    // 'save_to_file' was called on a database that wasn't built by
+    // 'load_from_file', so there's no 'units.yaml' path to write back to.
+    NoSourcePath,
+}
+
+impl Error for UnitDbError
+{
+    fn description(&self) -> &str
+    {
+        match *self
+        {
+        UnitDbError::Io(..) => "I/O error while accessing the units file",
+        UnitDbError::Yaml(..) => "failed to parse units file as YAML",
+        UnitDbError::UnknownUnitType(..) => "unrecognized unit type",
+        UnitDbError::NoSourcePath => "database has no units.yaml path to save to",
+        }
+    }
+
+    fn cause(&self) -> Option<&Error>
+    {
+        match *self
+        {
+        UnitDbError::Io(ref err) => Some(err),
+        UnitDbError::Yaml(ref err) => Some(err),
+        _ => None,
+        }
+    }
+}
+
+impl Display for UnitDbError
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result
+    {
+        match *self
+        {
+        UnitDbError::Io(ref err) => write!(f, "{}: {}", self.description(), err),
+        UnitDbError::Yaml(ref err) => write!(f, "{}: {}", self.description(), err),
+        UnitDbError::UnknownUnitType(ref found) => write!(f, "{}: \'{}\'", self.description(), found),
+        UnitDbError::NoSourcePath => write!(f, "{}", self.description()),
+        }
+    }
+}
+
+impl From<io::Error> for UnitDbError
+{
+    fn from(err: io::Error) -> UnitDbError
+    {
+        UnitDbError::Io(err)
+    }
+}
+
+impl From<::serde_yaml::Error> for UnitDbError
+{
+    fn from(err: ::serde_yaml::Error) -> UnitDbError
+    {
+        UnitDbError::Yaml(err)
+    }
+}
+
+// A parsed 'define' command argument: "<name>:<type>:<conv_factor>" with an
+// optional trailing comma-separated alias list, ex.
+// "furlong:length:201.168:fur,furlongs". Mirrors 'convert::FormatSpec's
+// colon-delimited grammar rather than inventing a new shape for a second
+// command's argument.
+#[derive(Debug)]
+pub struct DefineSpec
+{
+    pub common_name: String,
+    pub unit_type: String,
+    pub conv_factor: f64,
+    pub aliases: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum DefineSpecError
+{
+    MissingField(&'static str),
+    BadConvFactor(String),
+}
+
+impl Error for DefineSpecError
+{
+    fn description(&self) -> &str
+    {
+        match *self
+        {
+        DefineSpecError::MissingField(..) => "missing field in define spec",
+        DefineSpecError::BadConvFactor(..) => "conversion factor is not a number",
+        }
+    }
+}
+
+impl Display for DefineSpecError
+{
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result
+    {
+        match *self
+        {
+        DefineSpecError::MissingField(ref field) => write!(f, "{}: {}", self.description(), field),
+        DefineSpecError::BadConvFactor(ref field) => write!(f, "{}: \'{}\'", self.description(), field),
+        }
+    }
+}
+
+impl FromStr for DefineSpec
+{
+    type Err = DefineSpecError;
+
+    // Tokenizes on ':' same as 'FormatSpec': name, then type, then
+    // conversion factor, then an optional comma-separated alias list.
+    fn from_str(spec: &str) -> Result<DefineSpec, DefineSpecError>
+    {
+        let mut fields = spec.split(':');
+
+        let common_name = match fields.next()
+        {
+        Some(name) if !name.is_empty() => name.to_string(),
+        _ => return Err(DefineSpecError::MissingField("common name")),
+        };
+
+        let unit_type = match fields.next()
+        {
+        Some(unit_type) if !unit_type.is_empty() => unit_type.to_string(),
+        _ => return Err(DefineSpecError::MissingField("unit type")),
+        };
+
+        let conv_factor = match fields.next()
+        {
+        Some(digits) => match digits.parse::<f64>()
+            {
+            Ok(value) => value,
+            Err(..) => return Err(DefineSpecError::BadConvFactor(digits.to_string())),
+            },
+        None => return Err(DefineSpecError::MissingField("conversion factor")),
+        };
+
+        let aliases = match fields.next()
+        {
+        Some(csv) if !csv.is_empty() => csv.split(',').map(|alias| alias.to_string()).collect(),
+        _ => Vec::new(),
+        };
+
+        Ok(DefineSpec { common_name: common_name, unit_type: unit_type,
+                         conv_factor: conv_factor, aliases: aliases })
+    }
+}
+
+// Levenshtein edit distance between 'a' and 'b', computed with the standard
+// two-row dynamic-programming recurrence (row i,j = min of delete
+// row[i-1][j]+1, insert row[i][j-1]+1, substitute row[i-1][j-1]+cost) so
+// only two rows of state are ever live instead of a full matrix.
+fn levenshtein(a: &str, b: &str) -> usize
+{
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    let mut prev_row: Vec<usize> = (0..b.len() + 1).collect();
+    let mut curr_row: Vec<usize> = vec![0; b.len() + 1];
+
+    for i in 1..a.len() + 1
+    {
+        curr_row[0] = i;
+
+        for j in 1..b.len() + 1
+        {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+
+            curr_row[j] = (prev_row[j] + 1)
+                .min(curr_row[j - 1] + 1)
+                .min(prev_row[j - 1] + cost);
+        }
+
+        mem::swap(&mut prev_row, &mut curr_row);
+    }
+
+    prev_row[b.len()]
 }
 
 // TODO refactor to make unit field private to ensure no initialization occurs without proper tracking
@@ -464,4 +928,4 @@ impl UnitInit
     {
         !(self.default_name || self.default_conv || self.default_type)
     }
-}
\ No newline at end of file
+}
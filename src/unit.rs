@@ -20,8 +20,77 @@
  */
 
 use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::ops;
 use std::rc::Rc;
 
+/* struct Symbol
+ *
+ * Description: an interned unit name / alias, represented as a small integer
+ *   instead of a heap-allocated string. Comparing and ordering two Symbols is
+ *   a single integer compare rather than a byte-by-byte string compare, and
+ *   a spelling that appears as both a common name and an alias (or in more
+ *   than one unit's alias list) only ever occupies one heap allocation. See
+ *   'SymbolTable'.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Symbol(u32);
+
+/* struct SymbolTable
+ *
+ * Description: interns unit name / alias strings into Symbols. 'intern'
+ *   assigns a spelling its Symbol the first time it's seen at config-load
+ *   time, reusing the same Symbol on every later occurrence of that
+ *   spelling. 'get' performs the reverse lookup without interning, for
+ *   callers (ex. 'UnitDatabase::query') checking arbitrary user input that
+ *   shouldn't grow the table just because a name was mistyped. 'resolve'
+ *   recovers the original text for display, ex. collision warnings and unit
+ *   listings.
+ */
+pub struct SymbolTable
+{
+	by_text: HashMap<String, Symbol>,
+	by_symbol: Vec<Rc<String>>,
+}
+
+impl SymbolTable
+{
+	pub fn new() -> SymbolTable
+	{
+		// intern the empty string first so 'Symbol(0)' - 'Unit::new()'s
+		// placeholder common_name before a real one is set - always resolves
+		// to something, the same as the old Rc::new(String::new()) default.
+		let mut table = SymbolTable { by_text: HashMap::new(), by_symbol: Vec::new() };
+		table.intern("");
+		table
+	}
+
+	pub fn intern(&mut self, text: &str) -> Symbol
+	{
+		if let Some(&sym) = self.by_text.get(text)
+		{
+			return sym;
+		}
+
+		let sym = Symbol(self.by_symbol.len() as u32);
+		self.by_symbol.push(Rc::new(text.to_string()));
+		self.by_text.insert(text.to_string(), sym);
+		sym
+	}
+
+	pub fn get(&self, text: &str) -> Option<Symbol>
+	{
+		self.by_text.get(text).cloned()
+	}
+
+	pub fn resolve(&self, sym: Symbol) -> &Rc<String>
+	{
+		&self.by_symbol[sym.0 as usize]
+	}
+}
+
 // unit types Yucon recognizes
 // statically allocated so that we do not waste memory storing duplicate data
 pub static UNIT_TYPES: [&'static str; 12] = ["area",
@@ -37,15 +106,73 @@ pub static UNIT_TYPES: [&'static str; 12] = ["area",
                                              "torque",
                                              "volume",];
 
+/* type BaseDimensions
+ *
+ * Description: the exponent of each of the seven SI base quantities -
+ *   length, mass, time, electric current, temperature, amount of substance,
+ *   luminous intensity, in that order - that make up a unit's type. Ex.
+ *   "force" (kg*m/s^2) is [1, 1, -2, 0, 0, 0, 0]. Used by the conversion
+ *   engine (see 'exec::dimension_signature') to check two unit expressions
+ *   for dimensional agreement by summing and comparing these vectors
+ *   instead of comparing 'unit_type' tags directly, so two differently
+ *   named types that happen to be dimensionally identical (ex. torque and
+ *   energy, both newton-meters) still convert against one another.
+ */
+pub type BaseDimensions = [i8; 7];
+
+pub const LENGTH: usize = 0;
+pub const MASS: usize = 1;
+pub const TIME: usize = 2;
+pub const CURRENT: usize = 3;
+pub const TEMPERATURE: usize = 4;
+pub const AMOUNT: usize = 5;
+pub const LUMINOSITY: usize = 6;
+
+/* Returns the canonical BaseDimensions for one of Yucon's recognized
+ * UNIT_TYPES. An unrecognized type (which 'get_unit_type' in config.rs
+ * should already have rejected before this is ever called) is treated as
+ * dimensionless so that it can never spuriously compare equal to a real
+ * unit's dimensions.
+ *
+ * Parameters:
+ *   - unit_type : one of the strings in UNIT_TYPES
+ */
+pub fn base_dims_for_type(unit_type: &'static str) -> BaseDimensions
+{
+	match unit_type
+	{
+	"area"         => [2, 0, 0, 0, 0, 0, 0],
+	"energy"       => [2, 1, -2, 0, 0, 0, 0],
+	"force"        => [1, 1, -2, 0, 0, 0, 0],
+	"fuel economy" => [-2, 0, 0, 0, 0, 0, 0], // length / volume
+	"length"       => [1, 0, 0, 0, 0, 0, 0],
+	"mass"         => [0, 1, 0, 0, 0, 0, 0],
+	"power"        => [2, 1, -3, 0, 0, 0, 0],
+	"pressure"     => [-1, 1, -2, 0, 0, 0, 0],
+	"speed"        => [1, 0, -1, 0, 0, 0, 0],
+	"temperature"  => [0, 0, 0, 0, 1, 0, 0],
+	"torque"       => [2, 1, -2, 0, 0, 0, 0], // same dimensions as energy
+	"volume"       => [3, 0, 0, 0, 0, 0, 0],
+	_              => [0, 0, 0, 0, 0, 0, 0],
+	}
+}
+
 // TODO: tracking defaults is only necessary during allocation. add an initilization wrapper
 #[derive(Debug)]
 pub struct Unit
 {
-	pub common_name: Rc<String>,
+	pub common_name: Symbol,
+	// 'common_name' resolved, cached at the same time as the Symbol itself so
+	// that 'Mul'/'Div'/'powi' below can compose a derived unit's display name
+	// (ex. "N*m") without needing a SymbolTable in scope. A unit built by one
+	// of those rather than by config loading has no meaningful Symbol - see
+	// their doc comments - and carries its name here only.
+	pub common_name_text: Rc<String>,
 	pub conv_factor: f64,
 	pub dimensions: u8,
 	pub inverse: bool,
 	pub unit_type: &'static str, //life time is static because the type strings are embedded
+	pub base_dims: BaseDimensions, // derived from unit_type, see 'base_dims_for_type'
 	pub zero_point: f64,
 	pub has_aliases: bool,
 	default_name: bool,
@@ -61,11 +188,13 @@ impl Unit
 	pub fn new() -> Unit
 	{
 		Unit {
-			common_name: Rc::new(String::new()),
+			common_name: Symbol(0),
+			common_name_text: Rc::new(String::new()),
 			conv_factor: 1.0,
 			dimensions: 1,
 			inverse: false,
 			unit_type: UNIT_TYPES[0],
+			base_dims: base_dims_for_type(UNIT_TYPES[0]),
 			zero_point: 0.0,
 			has_aliases: false,
 			default_name: true,
@@ -77,11 +206,12 @@ impl Unit
 		}
 	}
 	
-	pub fn set_common_name(&mut self, name: String)
+	pub fn set_common_name(&mut self, name: Symbol, symbols: &SymbolTable)
 	{
 		if self.default_name
 		{
-			self.common_name = Rc::new(name);
+			self.common_name = name;
+			self.common_name_text = symbols.resolve(name).clone();
 			self.default_name = false;
 		}
 		else
@@ -92,8 +222,8 @@ impl Unit
 			// in config triggers a flush of the current unit and starts a new one.
 		}
 	}
-	
-	pub fn set_conv_factor(&mut self, conv_factor: f64)
+
+	pub fn set_conv_factor(&mut self, conv_factor: f64, symbols: &SymbolTable)
 	{
 		if self.default_conv
 		{
@@ -104,11 +234,11 @@ impl Unit
 		{
 			println!("\n*** WARNING ***\n\
 			          For unit {}: attemtped to assign conv_factor twice. Ignoring this attempt.\n",
-			          self.common_name);
+			          symbols.resolve(self.common_name));
 		}
 	}
-	
-	pub fn set_dimensions(&mut self, dimensions: u8)
+
+	pub fn set_dimensions(&mut self, dimensions: u8, symbols: &SymbolTable)
 	{
 		if self.default_dims
 		{
@@ -119,11 +249,11 @@ impl Unit
 		{
 			println!("\n*** WARNING ***\n\
 			          For unit {}: attemtped to assign dimensions twice. Ignoring this attempt.\n",
-			          self.common_name);
+			          symbols.resolve(self.common_name));
 		}
 	}
-	
-	pub fn set_inverse(&mut self, inverse: bool)
+
+	pub fn set_inverse(&mut self, inverse: bool, symbols: &SymbolTable)
 	{
 		if self.default_inv
 		{
@@ -134,26 +264,27 @@ impl Unit
 		{
 			println!("\n*** WARNING ***\n\
 			          For unit {}: attemtped to assign inverse twice. Ignoring this attempt.\n",
-			          self.common_name);
+			          symbols.resolve(self.common_name));
 		}
 	}
-	
-	pub fn set_unit_type(&mut self, unit_type: &'static str)
+
+	pub fn set_unit_type(&mut self, unit_type: &'static str, symbols: &SymbolTable)
 	{
 		if self.default_type
 		{
 			self.unit_type = unit_type;
+			self.base_dims = base_dims_for_type(unit_type);
 			self.default_type = false;
 		}
 		else
 		{
 			println!("\n*** WARNING ***\n\
 			          For unit {}: attemtped to assign unit_type twice. Ignoring this attempt.\n",
-			          self.common_name);
+			          symbols.resolve(self.common_name));
 		}
 	}
-	
-	pub fn set_zero_point(&mut self, zero_point: f64)
+
+	pub fn set_zero_point(&mut self, zero_point: f64, symbols: &SymbolTable)
 	{
 		if self.default_zpt
 		{
@@ -164,7 +295,7 @@ impl Unit
 		{
 			println!("\n*** WARNING ***\n\
 			          For unit {}: attemtped to assign zero_point twice. Ignoring this attempt.\n",
-			          self.common_name);
+			          symbols.resolve(self.common_name));
 		}
 	}
 	
@@ -172,6 +303,180 @@ impl Unit
 	{
 		!(self.default_name || self.default_conv || self.default_type)
 	}
+
+	/* powi
+	 *
+	 * Description: raises this unit to an integer power, ex. "m".powi(2)
+	 *   becomes an area unit equivalent to "m^2". conv_factor is raised to
+	 *   the same power, base_dims and dimensions are scaled by it, and
+	 *   common_name_text is suffixed with "^N" (or left bare for N == 1, the
+	 *   same convention 'exec::render_compound' uses). Rejects affine/
+	 *   inverted units for the same reason 'Mul'/'Div' do - see their doc
+	 *   comment above 'combine'.
+	 */
+	pub fn powi(&self, exponent: i32) -> Result<Unit, UnitAlgebraError>
+	{
+		if self.zero_point != 0.0 || self.inverse
+		{
+			return Err(UnitAlgebraError::NonRatioUnit(self.common_name_text.clone()));
+		}
+
+		let mut base_dims = self.base_dims;
+		for dim in base_dims.iter_mut()
+		{
+			*dim *= exponent as i8;
+		}
+
+		let common_name_text = Rc::new(if exponent == 1
+		{
+			self.common_name_text.to_string()
+		}
+		else
+		{
+			format!("{}^{}", self.common_name_text, exponent)
+		});
+
+		Ok(Unit {
+			common_name: Symbol(0),
+			common_name_text,
+			conv_factor: self.conv_factor.powi(exponent),
+			dimensions: (self.dimensions as i32 * exponent).max(0) as u8,
+			inverse: false,
+			unit_type: "",
+			base_dims,
+			zero_point: 0.0,
+			has_aliases: false,
+			default_name: false,
+			default_conv: false,
+			default_dims: false,
+			default_inv: false,
+			default_type: false,
+			default_zpt: false,
+		})
+	}
+}
+
+/* enum UnitAlgebraError
+ *
+ * Description: why 'Mul'/'Div'/'powi' on a 'Unit' failed. The only failure
+ *   mode is combining a non-ratio unit - one with a nonzero 'zero_point'
+ *   (ex. a temperature scale) or 'inverse' set (ex. fuel economy) - since
+ *   multiplying or dividing an offset or reciprocal scale by anything has no
+ *   well-defined meaning. This mirrors 'exec::ConversionError::NonRatioUnit',
+ *   which rejects the same units for the same reason when they show up as a
+ *   factor of a parsed compound expression.
+ */
+#[derive(Debug, Clone)]
+pub enum UnitAlgebraError
+{
+	NonRatioUnit(Rc<String>),
+}
+
+impl Error for UnitAlgebraError
+{
+	fn description(&self) -> &str
+	{
+		match *self
+		{
+		UnitAlgebraError::NonRatioUnit(..) => "offset or inverse units cannot be combined via unit algebra",
+		}
+	}
+}
+
+impl fmt::Display for UnitAlgebraError
+{
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result
+	{
+		match *self
+		{
+		UnitAlgebraError::NonRatioUnit(ref name) =>
+			write!(f, "\'{}\' offset or inverse units cannot be combined via unit algebra", name),
+		}
+	}
+}
+
+// Shared by every 'Mul'/'Div' impl below: validates both operands are ratio
+// units, then combines conv_factor, base_dims, dimensions and
+// common_name_text the way 'divide' indicates. 'divide' is false for Mul's
+// sign convention (sum dimensions, "lhs*rhs") and true for Div's (subtract
+// dimensions, "lhs/rhs").
+fn combine(lhs: &Unit, rhs: &Unit, divide: bool) -> Result<Unit, UnitAlgebraError>
+{
+	if lhs.zero_point != 0.0 || lhs.inverse
+	{
+		return Err(UnitAlgebraError::NonRatioUnit(lhs.common_name_text.clone()));
+	}
+
+	if rhs.zero_point != 0.0 || rhs.inverse
+	{
+		return Err(UnitAlgebraError::NonRatioUnit(rhs.common_name_text.clone()));
+	}
+
+	let sign: i8 = if divide { -1 } else { 1 };
+	let mut base_dims: BaseDimensions = [0; 7];
+
+	for i in 0..base_dims.len()
+	{
+		base_dims[i] = lhs.base_dims[i] + sign * rhs.base_dims[i];
+	}
+
+	// a derived unit's own 'dimensions' (the metric-prefix exponent) only
+	// matters if a prefix is ever applied directly to it; clamp a Div's
+	// negative result to 0 rather than let it wrap through u8.
+	let dimensions = (lhs.dimensions as i32 + sign as i32 * rhs.dimensions as i32).max(0) as u8;
+
+	let conv_factor = if divide { lhs.conv_factor / rhs.conv_factor } else { lhs.conv_factor * rhs.conv_factor };
+	let common_name_text = Rc::new(if divide
+	{
+		format!("{}/{}", lhs.common_name_text, rhs.common_name_text)
+	}
+	else
+	{
+		format!("{}*{}", lhs.common_name_text, rhs.common_name_text)
+	});
+
+	Ok(Unit {
+		common_name: Symbol(0), // derived units aren't looked up by name; see 'common_name_text'
+		common_name_text,
+		conv_factor,
+		dimensions,
+		inverse: false,
+		unit_type: "", // no single UNIT_TYPES entry describes an arbitrary compound; base_dims is authoritative
+		base_dims,
+		zero_point: 0.0,
+		has_aliases: false,
+		default_name: false,
+		default_conv: false,
+		default_dims: false,
+		default_inv: false,
+		default_type: false,
+		default_zpt: false,
+	})
+}
+
+// Implemented for '&Unit' rather than 'Rc<Unit>' directly: 'Rc' isn't a
+// fundamental type under the orphan rules, so a foreign trait like 'Mul'
+// can't be implemented for 'Rc<Unit>' from this crate. A caller holding an
+// 'Rc<Unit>' uses these the same way any deref coercion site does - ex.
+// '&*a * &*b' or 'a.as_ref() * b.as_ref()'.
+impl<'a, 'b> ops::Mul<&'b Unit> for &'a Unit
+{
+	type Output = Result<Unit, UnitAlgebraError>;
+
+	fn mul(self, rhs: &'b Unit) -> Self::Output
+	{
+		combine(self, rhs, false)
+	}
+}
+
+impl<'a, 'b> ops::Div<&'b Unit> for &'a Unit
+{
+	type Output = Result<Unit, UnitAlgebraError>;
+
+	fn div(self, rhs: &'b Unit) -> Self::Output
+	{
+		combine(self, rhs, true)
+	}
 }
 
 /*
@@ -224,7 +529,8 @@ impl<'a> UnitScalar<'a>
  */
 pub struct UnitDatabase
 {
-	aliases: BTreeMap<Rc<String>, Rc<Unit>>,
+	symbols: SymbolTable,
+	aliases: BTreeMap<Symbol, Rc<Unit>>,
 	units: Vec<Rc<Unit>>
 }
 
@@ -232,10 +538,34 @@ impl UnitDatabase
 {
 	pub fn new() -> UnitDatabase
 	{
-		UnitDatabase { aliases: BTreeMap::new(),
+		UnitDatabase { symbols: SymbolTable::new(),
+		               aliases: BTreeMap::new(),
 		               units: Vec::new() }
 	}
 
+	// Interns 'text' into this database's symbol table, for callers (ex.
+	// config loading) assembling a Unit's common name / aliases before
+	// handing it to 'add'.
+	pub fn intern(&mut self, text: &str) -> Symbol
+	{
+		self.symbols.intern(text)
+	}
+
+	// Recovers the original spelling behind a Symbol this database handed
+	// out, for collision warnings and unit listings.
+	pub fn resolve(&self, sym: Symbol) -> &Rc<String>
+	{
+		self.symbols.resolve(sym)
+	}
+
+	// Exposes the underlying symbol table directly, for callers that need
+	// to resolve a Unit's common_name before the Unit has been (or ever is)
+	// added to this database.
+	pub fn symbols(&self) -> &SymbolTable
+	{
+		&self.symbols
+	}
+
 	/**
 	# add()
 
@@ -260,7 +590,7 @@ impl UnitDatabase
 	Success: None
 	Failure: Some
 	*/
-	pub fn add(&mut self, unit: Unit, aliases: &Vec<Rc<String>>) -> Option<Unit>
+	pub fn add(&mut self, unit: Unit, aliases: &Vec<Symbol>) -> Option<Unit>
 	{
 		let mut exists = false;
 
@@ -268,7 +598,7 @@ impl UnitDatabase
 		{
 			exists = true;
 		}
-		
+
 		if unit.has_aliases
 		{
 			for alias in aliases
@@ -283,7 +613,7 @@ impl UnitDatabase
 
 		if !exists
 		{
-			let common_name = unit.common_name.clone();
+			let common_name = unit.common_name;
 			let has_aliases = unit.has_aliases;
 			let unit_rc = Rc::new(unit);
 
@@ -294,7 +624,7 @@ impl UnitDatabase
 			{
 				for alias in aliases
 				{
-					self.aliases.insert(alias.clone(), unit_rc.clone());
+					self.aliases.insert(*alias, unit_rc.clone());
 				}
 			}
 
@@ -306,9 +636,12 @@ impl UnitDatabase
 
 	pub fn query(&self, name: &String) -> Option<Rc<Unit>>
 	{
-		if let Some(unit_rc) = self.aliases.get(&Rc::new(name.clone()))
-		{ 
-			return Some(unit_rc.clone());
+		if let Some(symbol) = self.symbols.get(name)
+		{
+			if let Some(unit_rc) = self.aliases.get(&symbol)
+			{
+				return Some(unit_rc.clone());
+			}
 		}
 
 		None
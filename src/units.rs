@@ -8,19 +8,59 @@ use std::{
     io::Read,
 };
 
-#[derive(Deserialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, PartialEq, Eq, Clone, Copy)]
 pub enum UnitType {
+    Area,
+    Energy,
+    Force,
+    FuelEconomy,
     Length,
+    Mass,
+    Power,
+    Pressure,
+    Speed,
+    Temperature,
+    Torque,
     Volume,
 }
 
-#[derive(Deserialize, Debug)]
-struct ConfigFileUnits {
+impl UnitType {
+    /// Maps one of the legacy lowercase unit-type strings (`unit::UNIT_TYPES`)
+    /// onto its equivalent variant here, for `config::migrate_legacy_config`.
+    /// Returns `None` for a string that doesn't name one of the recognized
+    /// types.
+    pub fn from_legacy_str(unit_type: &str) -> Option<UnitType> {
+        Some(match unit_type {
+            "area" => UnitType::Area,
+            "energy" => UnitType::Energy,
+            "force" => UnitType::Force,
+            "fuel economy" => UnitType::FuelEconomy,
+            "length" => UnitType::Length,
+            "mass" => UnitType::Mass,
+            "power" => UnitType::Power,
+            "pressure" => UnitType::Pressure,
+            "speed" => UnitType::Speed,
+            "temperature" => UnitType::Temperature,
+            "torque" => UnitType::Torque,
+            "volume" => UnitType::Volume,
+            _ => return None,
+        })
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct ConfigFileUnits {
     units: Vec<ConfigFileUnit>,
 }
 
-#[derive(Deserialize, Debug)]
-struct ConfigFileUnit {
+impl From<Vec<UnitParams>> for ConfigFileUnits {
+    fn from(units: Vec<UnitParams>) -> Self {
+        ConfigFileUnits { units: units.into_iter().map(ConfigFileUnit::from).collect() }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct ConfigFileUnit {
     unit: UnitParams
 }
 
@@ -32,14 +72,42 @@ impl Deref for ConfigFileUnit {
     }
 }
 
-#[derive(Deserialize, Debug)]
-struct UnitParams {
+impl From<UnitParams> for ConfigFileUnit {
+    fn from(unit: UnitParams) -> Self {
+        ConfigFileUnit { unit }
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub(crate) struct UnitParams {
     name: String,
     unit_type: UnitType,
     conversion_factor: f64,
     aliases: Option<Vec<String>>,
     dimensions: Option<u32>,
     tags: Option<Vec<String>>,
+    zero_point: Option<f64>,
+    inverse: Option<bool>,
+}
+
+impl UnitParams {
+    /// Builds params for a unit migrated from the legacy line-oriented
+    /// `units.cfg` format - see `config::migrate_legacy_config`. `aliases`
+    /// is stored as `None` rather than `Some(vec![])` when empty, matching
+    /// what a hand-written `units.yaml` entry without an `aliases:` key
+    /// deserializes to.
+    pub fn from_legacy(name: String, unit_type: UnitType, conversion_factor: f64, aliases: Vec<String>, dimensions: u32, zero_point: f64, inverse: bool) -> UnitParams {
+        UnitParams {
+            name,
+            unit_type,
+            conversion_factor,
+            aliases: if aliases.is_empty() { None } else { Some(aliases) },
+            dimensions: Some(dimensions),
+            tags: None,
+            zero_point: Some(zero_point),
+            inverse: Some(inverse),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -50,6 +118,8 @@ pub struct Unit {
     pub aliases: Vec<Rc<String>>,
     pub dimensions: u32,
     pub tags: Vec<Rc<String>>,
+    pub zero_point: f64,
+    pub inverse: bool,
 }
 
 impl Unit {
@@ -64,8 +134,57 @@ impl Unit {
     pub fn has_tags(&self) -> bool {
         self.tags.len() > 0
     }
+
+    /// Converts `value`, expressed in this unit, into `to`. Units of
+    /// different `UnitType`s (ex. `Length` into `Temperature`) can never be
+    /// meaningfully converted and are rejected outright.
+    ///
+    /// A normal/affine unit (`inverse: false`) is converted by going through
+    /// a common base: `base = value * self.conversion_factor + self.zero_point`,
+    /// then `result = (base - to.zero_point) / to.conversion_factor`. This is
+    /// what makes ex. Celsius <-> Fahrenheit work despite neither scale's
+    /// zero lining up with the other's.
+    ///
+    /// A reciprocal unit (`inverse: true`, ex. mpg vs. L/100km) instead
+    /// applies its factor by division: `base = self.conversion_factor / value`
+    /// and `result = to.conversion_factor / base`. `value == 0.0` has no
+    /// reciprocal and is rejected rather than dividing by zero.
+    pub fn convert(&self, value: f64, to: &Unit) -> Result<f64, ConversionError> {
+        if self.unit_type != to.unit_type {
+            return Err(ConversionError::TypeMismatch);
+        }
+
+        if self.inverse || to.inverse {
+            if value == 0.0 {
+                return Err(ConversionError::DivideByZero);
+            }
+
+            let base = self.conversion_factor / value;
+            Ok(to.conversion_factor / base)
+        } else {
+            let base = value * self.conversion_factor + self.zero_point;
+            Ok((base - to.zero_point) / to.conversion_factor)
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConversionError {
+    TypeMismatch,
+    DivideByZero,
+}
+
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            ConversionError::TypeMismatch => write!(f, "cannot convert between units of different types"),
+            ConversionError::DivideByZero => write!(f, "cannot convert a value of zero through a reciprocal unit"),
+        }
+    }
 }
 
+impl std::error::Error for ConversionError {}
+
 impl From<ConfigFileUnit> for Unit {
     fn from(cfg_unit: ConfigFileUnit) -> Self {
         let mut unit = Unit {
@@ -87,6 +206,8 @@ impl From<ConfigFileUnit> for Unit {
                 .into_iter()
                 .map(|tag| Rc::new(tag))
                 .collect(),
+            zero_point: cfg_unit.unit.zero_point.unwrap_or(0.0),
+            inverse: cfg_unit.unit.inverse.unwrap_or(false),
         };
 
         // add the unit's common name into its aliases vector
@@ -105,6 +226,7 @@ pub struct UnitDatabase {
     pub default_namespace: Rc<String>
 }
 
+#[derive(Debug)]
 pub struct NameCollision {
     pub namespace: Rc<String>,
     pub alias: Rc<String>
@@ -116,53 +238,112 @@ impl Display for NameCollision {
     }
 }
 
+/// Everything that can go wrong loading a `UnitDatabase` from a units.yaml
+/// file or inserting a single unit into one, in place of the `error!`/
+/// `warn!`/`info!` logging this used to rely on. Each variant keeps the
+/// underlying `source` error (or the actual `NameCollision`s) so an embedder
+/// can tell a missing file from a malformed document from a naming conflict
+/// instead of just seeing `None`/`false`.
+#[derive(Debug)]
+pub enum UnitDbError {
+    FileOpen { path: String, source: std::io::Error },
+    FileRead { source: std::io::Error },
+    FileWrite { path: String, source: std::io::Error },
+    Deserialize { source: serde_yaml::Error },
+    Serialize { source: serde_yaml::Error },
+    Collisions { collisions: Vec<NameCollision> },
+}
+
+impl Display for UnitDbError {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            UnitDbError::FileOpen { path, source } => write!(f, "could not open '{}': {}", path, source),
+            UnitDbError::FileRead { source } => write!(f, "failed to read units.yaml: {}", source),
+            UnitDbError::FileWrite { path, source } => write!(f, "could not write '{}': {}", path, source),
+            UnitDbError::Deserialize { source } => write!(f, "failed to deserialize units.yaml: {}", source),
+            UnitDbError::Serialize { source } => write!(f, "failed to serialize units.yaml: {}", source),
+            UnitDbError::Collisions { collisions } => {
+                write!(f, "one or more units were not added due to name collisions: ")?;
+
+                for (i, collision) in collisions.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "; ")?;
+                    }
+
+                    write!(f, "{}", collision)?;
+                }
+
+                Ok(())
+            },
+        }
+    }
+}
+
+impl std::error::Error for UnitDbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            UnitDbError::FileOpen { source, .. } => Some(source),
+            UnitDbError::FileRead { source } => Some(source),
+            UnitDbError::FileWrite { source, .. } => Some(source),
+            UnitDbError::Deserialize { source } => Some(source),
+            UnitDbError::Serialize { source } => Some(source),
+            UnitDbError::Collisions { .. } => None,
+        }
+    }
+}
+
+/// Collision-resolution policy for `UnitDatabase::add`, naming the three ways
+/// a unit can be added when one of its aliases already exists in one of its
+/// namespaces.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OnCollision {
+    /// Add nothing; report every colliding alias.
+    Reject,
+    /// Replace the colliding alias->unit mappings with the new unit.
+    Override,
+    /// Keep the existing alias->unit mappings; index the new unit under
+    /// whichever of its aliases didn't collide.
+    KeepBoth,
+}
+
 impl UnitDatabase {
     pub const DEFAULT_NAMESPACE: &'static str = "default";
 
-    fn parse_file(mut self, path: String) -> Option<Self> {
+    fn parse_file(mut self, path: String) -> Result<Self, UnitDbError> {
         const FILE_BUFFER_SIZE: usize = 131072; // this should be plenty barring stupid tier modifications
         let mut file_as_string = String::with_capacity(FILE_BUFFER_SIZE);
-        let mut cfg_file = match File::open(path) {
-            Err(err) => {
-                error!("Could not load 'units.yaml': {}", err);
-                return None;
-            },
-            Ok(file) => file,
-        };
+        let mut cfg_file = File::open(&path)
+            .map_err(|source| UnitDbError::FileOpen { path: path.clone(), source })?;
 
-        match cfg_file.read_to_string(&mut file_as_string) {
-            Err(err) => {
-                error!("Failed to read 'units.yaml': {}", err);
-                return None;
-            },
-            _ => (),
-        }
+        cfg_file.read_to_string(&mut file_as_string)
+            .map_err(|source| UnitDbError::FileRead { source })?;
 
-        let units: ConfigFileUnits = match serde_yaml::from_str(&file_as_string) {
-            Err(err) => {
-                error!("Failed to deserialize 'units.yaml': {}", err);
-                return None;
-            },
-            Ok(parsed_yaml) => parsed_yaml,
-        };
+        let units: ConfigFileUnits = serde_yaml::from_str(&file_as_string)
+            .map_err(|source| UnitDbError::Deserialize { source })?;
         let units: Vec<Rc<Unit>> = units.units.into_iter().map(|unit| Rc::new(Unit::from(unit))).collect();
 
+        // loading the whole file is still best-effort: a unit whose name or
+        // aliases collide with one already in the database is skipped rather
+        // than aborting the load. 'add' below is what gives a caller that
+        // inserts a single unit on its own (ex. a runtime 'define' command)
+        // the collision detail; here we only care about the file itself
+        // having been well-formed.
         units.into_iter().for_each(|unit| {
-            self.add(unit);
+            let _ = self.add(unit, OnCollision::Reject);
         });
 
-        Some(self)
+        Ok(self)
     }
 
-    pub fn load_from_file(units_cfg: String, preferred_namespace: Option<String>) -> Option<Self> {
+    pub fn load_from_file(units_cfg: String, preferred_namespace: Option<String>) -> Result<Self, UnitDbError> {
         let default_namespace = Rc::new(Self::DEFAULT_NAMESPACE.to_string());
         let preferred_namespace = preferred_namespace
             .map(|namespace| Rc::new(namespace))
             .unwrap_or(default_namespace.clone());
-        
+
         let mut namespaces = BTreeMap::new();
         namespaces.insert(default_namespace.clone(), BTreeMap::new());
-        
+
         if default_namespace != preferred_namespace {
             namespaces.insert(preferred_namespace.clone(), BTreeMap::new());
         }
@@ -239,42 +420,122 @@ impl UnitDatabase {
         collision
     }
 
-    /// Adds a unit the database if neither its name nor any of its aliases exist
-    /// in the database under any of its listed tags. Returns `true` on success.
-    /// Otherwise `false` will be returned to indicate failure.
-    pub fn add(&mut self, unit: Rc<Unit>) -> bool {
-        if let Some(collisions) = self.check_collisions(unit.clone()) {
-            warn!("Unit with name '{}' will not be added. One or more of the unit's aliases is already registered in the database", unit.name);
-            collisions.iter().for_each(|collision| info!("{}", collision));
+    /// Inserts `unit` into `self.units` and indexes `aliases` (a subset of
+    /// `unit.aliases`, picked by `add`'s collision policy) into every
+    /// namespace `unit` belongs to - its tags, or the default namespace if
+    /// it's untagged. Returns whichever units got displaced out of an
+    /// alias->unit mapping by this call, for `add`'s `Override` policy to
+    /// prune.
+    fn insert_unit(&mut self, unit: &Rc<Unit>, aliases: &[Rc<String>]) -> Vec<Rc<Unit>> {
+        self.units.push(unit.clone());
+
+        let tags: Vec<Rc<String>> = if unit.has_tags() {
+            unit.tags.clone()
+        } else {
+            vec![self.default_namespace.clone()]
+        };
+
+        let mut displaced = Vec::new();
+
+        for tag in tags {
+            let namespace = self.namespaces.entry(tag).or_insert_with(BTreeMap::new);
 
-            return false;
+            for alias in aliases {
+                if let Some(old) = namespace.insert(alias.clone(), unit.clone()) {
+                    displaced.push(old);
+                }
+            }
         }
 
-        self.units.push(unit.clone());
+        displaced
+    }
 
-        if unit.has_tags() {
-            unit.tags.iter().for_each(|tag| {
-                // let namespace = if !self.namespaces.contains_key(tag) {
-                //     self.namespaces.insert(tag.clone(), BTreeMap::new());
-                //     self.namespaces.get_mut(tag).unwrap()
-                // } else {
-                //     self.namespaces.get_mut(tag).unwrap()
-                // };
-                let namespace = self.namespaces.entry(tag.clone()).or_insert(BTreeMap::new());
-
-                unit.aliases.iter().for_each(|alias| {
-                    namespace.insert(alias.clone(), unit.clone());
-                });
-            });
-        } else {
-            let namespace = self.namespaces.get_mut(&self.default_namespace).expect("default namespace is always present");
-            
-            unit.aliases.iter().for_each(|alias| {
-                namespace.insert(alias.clone(), unit.clone());
-            });
+    /// Drops any unit in `displaced` from `self.units` once none of its
+    /// aliases point to it in any namespace anymore, ie it was completely
+    /// shadowed by an `OnCollision::Override` insert rather than merely
+    /// losing one of several aliases.
+    fn prune_orphans(&mut self, displaced: Vec<Rc<Unit>>) {
+        for old in displaced {
+            let still_referenced = self.namespaces.values()
+                .any(|namespace| namespace.values().any(|unit| Rc::ptr_eq(unit, &old)));
+
+            if !still_referenced {
+                self.units.retain(|unit| !Rc::ptr_eq(unit, &old));
+            }
+        }
+    }
+
+    /// Adds a unit to the database. `on_collision` decides what happens when
+    /// one of `unit`'s aliases already names a unit in one of its namespaces:
+    ///   - `Reject`: the unit is not added at all, and every collision found
+    ///     is returned in `UnitDbError::Collisions`. This is what a bulk
+    ///     config load uses so a later duplicate never clobbers an earlier
+    ///     definition by accident.
+    ///   - `Override`: the colliding alias->unit mappings are replaced with
+    ///     this unit, and any unit left with no remaining aliases anywhere
+    ///     is pruned from the database. This is what lets a user config
+    ///     deliberately shadow a built-in unit within a namespace.
+    ///   - `KeepBoth`: the colliding aliases keep pointing at the existing
+    ///     unit, but `unit`'s non-colliding aliases are still indexed, so it
+    ///     remains reachable under whichever of its names didn't conflict.
+    pub fn add(&mut self, unit: Rc<Unit>, on_collision: OnCollision) -> Result<(), UnitDbError> {
+        let collisions = self.check_collisions(unit.clone());
+
+        match on_collision {
+            OnCollision::Reject => {
+                if let Some(collisions) = collisions {
+                    return Err(UnitDbError::Collisions { collisions });
+                }
+
+                self.insert_unit(&unit, &unit.aliases);
+            },
+            OnCollision::Override => {
+                let displaced = self.insert_unit(&unit, &unit.aliases);
+                self.prune_orphans(displaced);
+            },
+            OnCollision::KeepBoth => {
+                let colliding: Vec<Rc<String>> = collisions
+                    .map(|collisions| collisions.into_iter().map(|collision| collision.alias).collect())
+                    .unwrap_or_default();
+                let aliases: Vec<Rc<String>> = unit.aliases.iter()
+                    .filter(|alias| !colliding.contains(alias))
+                    .cloned()
+                    .collect();
+
+                self.insert_unit(&unit, &aliases);
+            },
         }
 
-        true
+        Ok(())
+    }
+
+    /// Lists every unit registered in `tag`'s namespace, or every unit in the
+    /// database if `tag` is `None`. A unit aliased several times within the
+    /// same namespace is only listed once.
+    pub fn list(&self, tag: Option<&String>) -> Vec<Rc<Unit>> {
+        match tag {
+            None => self.units.clone(),
+            Some(tag) => {
+                let mut units: Vec<Rc<Unit>> = Vec::new();
+
+                if let Some(namespace) = self.namespaces.get(tag) {
+                    for unit in namespace.values() {
+                        if !units.iter().any(|listed| Rc::ptr_eq(listed, unit)) {
+                            units.push(unit.clone());
+                        }
+                    }
+                }
+
+                units
+            },
+        }
+    }
+
+    /// Iterates every namespace (tag) currently registered in the database,
+    /// including the default namespace and the preferred namespace even if
+    /// nothing has been added to them yet.
+    pub fn namespaces(&self) -> impl Iterator<Item = &Rc<String>> {
+        self.namespaces.keys()
     }
 
     pub fn query(&self, name: &String, tag: Option<&String>) -> Option<Rc<Unit>> {
@@ -0,0 +1,410 @@
+/* utils.rs
+ * ===
+ * Shared tokenizing/syntax-checking primitives used by the 'runtime' module's
+ * line parser and its prefix-aware unit/conversion code - the same
+ * responsibilities 'parse.rs' and the top of 'exec.rs' serve for the older
+ * interpreter. Kept as a separate module because 'runtime' is being evolved
+ * along its own track rather than reusing 'parse'/'exec' directly.
+ *
+ * This file is a part of:
+ *
+ * Yucon - General Purpose Unit Converter
+ * Copyright (C) 2016-2017  Blaine Murphy
+ *
+ * This program is free software: you can redistribute it and/or modify it under the terms
+ * of the GNU General Public License as published by the Free Software Foundation, either
+ * version 3 of the License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful, but WITHOUT ANY WARRANTY;
+ * without even the implied warranty of MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.
+ * See the GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::error;
+use std::error::Error;
+use std::fmt;
+use std::fmt::Formatter;
+use std::fmt::Display;
+use std::ops::Range;
+
+#[derive(Debug, Clone)]
+pub enum SyntaxError
+{
+	Expected(Range<usize>, String),
+	BadEscSeq(Range<usize>, char),
+}
+
+impl SyntaxError
+{
+	// Returns the human readable message carried by this error, without the
+	// "syntax error @ col N" framing that Display adds.
+	pub fn message(&self) -> String
+	{
+		match *self
+		{
+		SyntaxError::Expected(_, ref msg) => format!("expected {}", msg),
+		SyntaxError::BadEscSeq(_, ref ch) => format!("bad escape sequence: \\{}", ch),
+		}
+	}
+
+	// Returns the character range of the offending span in the original input line.
+	pub fn range(&self) -> Range<usize>
+	{
+		match *self
+		{
+		SyntaxError::Expected(ref range, _) => range.clone(),
+		SyntaxError::BadEscSeq(ref range, _) => range.clone(),
+		}
+	}
+
+	// Renders the original line with the offending span underlined by carets,
+	// the way rustc / rust-analyzer point at a `TextRange` beneath a line of
+	// source. Meant for CLI presentation of parse failures.
+	pub fn render_carets(&self, line: &str) -> String
+	{
+		let range = self.range();
+		let start = range.start.min(line.chars().count());
+		let end = range.end.max(start + 1);
+		let mut carets = String::with_capacity(end);
+
+		for _ in 0..start
+		{
+			carets.push(' ');
+		}
+		for _ in start..end
+		{
+			carets.push('^');
+		}
+
+		format!("{}\n{}", line, carets)
+	}
+}
+
+impl Error for SyntaxError
+{
+	fn description(&self) -> &str
+	{
+		match *self
+		{
+		SyntaxError::Expected(..) => "expected different token",
+		SyntaxError::BadEscSeq(..) => "reached bad escape sequence",
+		}
+	}
+
+	fn cause(&self) -> Option<&Error>
+	{
+		None
+	}
+}
+
+impl Display for SyntaxError
+{
+	fn fmt(&self, f: &mut Formatter) -> fmt::Result
+	{
+		match *self
+		{
+		SyntaxError::Expected(ref range, ref msg) => {
+			write!(f, "syntax error @ col {}: expected {}", range.start+1, msg)
+		},
+		SyntaxError::BadEscSeq(ref range, ref ch) => {
+			write!(f, "syntax error @ col {}: bad escape sequence: \\{}", range.start+1, ch)
+		},
+		}
+	}
+}
+
+/* trait SyntaxChecker
+ *
+ * Description: generic trait representing a token-based syntax, allowing
+ *   wildly different syntaxes to be handled by the same tokenization routine
+ *   and be validated at the time of tokenization. See 'fn tokenize'.
+ */
+pub trait SyntaxChecker
+{
+	fn feed_token(&mut self, token: &str, delim: bool, range: Range<usize>) -> bool;
+	fn is_esc(&self, ch: char) -> bool;
+	fn is_comment(&self, ch: char) -> bool;
+	fn is_delim(&self, ch: char) -> bool;
+	fn is_preserved_delim(&self, ch: char) -> bool;
+	fn esc_char(&self) -> char;
+	fn valid(&self) -> bool;
+	fn assert_valid(&self, range: Range<usize>, more_tokens: bool) -> Result<(), SyntaxError>;
+	fn esc_set(&self) -> bool;
+	fn set_esc(&mut self, set: bool);
+	fn reset(&mut self);
+}
+
+const DELIM: bool = true; // constant for indicated delimiter to SyntaxChecker trait
+
+/* struct Position
+ *
+ * Description: a character offset into the original input line together with a
+ *   span length. Carried on every TokenType so callers downstream of
+ *   'tokenize' can point a caret at the exact characters that produced an
+ *   error rather than just the token's index.
+ */
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Position
+{
+	pub offset: usize,
+	pub len: usize,
+}
+
+impl Position
+{
+	pub fn new(offset: usize, len: usize) -> Position
+	{
+		Position { offset: offset, len: len }
+	}
+
+	// Recovers the character range this position covers in the original line.
+	pub fn range(&self) -> Range<usize>
+	{
+		self.offset..(self.offset + self.len)
+	}
+}
+
+impl From<Range<usize>> for Position
+{
+	fn from(range: Range<usize>) -> Position
+	{
+		Position { offset: range.start, len: range.end.saturating_sub(range.start) }
+	}
+}
+
+/* enum TokenType
+ *
+ * Description: wrapper for tokens that denotes them as either delimiters or
+ *   normal tokens, each carrying the 'Position' it occupied in the original
+ *   input line.
+ */
+#[derive(Debug, Clone)]
+pub enum TokenType
+{
+	Delim (String, Position),
+	Normal(String, Position),
+}
+
+impl TokenType
+{
+	// Conveniently unwraps the contained string so redundant match lookups are
+	// eliminated.
+	pub fn unwrap(self) -> String
+	{
+		match self
+		{
+			TokenType::Delim(tok, _)  => return tok,
+			TokenType::Normal(tok, _) => return tok,
+		}
+	}
+
+	// Peeks at the wrapped value. Returns reference to String for convenience
+	// when working with borrowed TokenTypes
+	pub fn peek(&self) -> &String
+	{
+		match *self
+		{
+		TokenType::Delim(ref tok, _) => tok,
+		TokenType::Normal(ref tok, _) => tok,
+		}
+	}
+
+	// Checks if the contained string is empty so that unwrapping is not
+	// necessary
+	pub fn is_empty(&self) -> bool
+	{
+		match *self
+		{
+			TokenType::Delim(ref tok, _)  => return tok.is_empty(),
+			TokenType::Normal(ref tok, _) => return tok.is_empty(),
+		}
+	}
+
+	// Returns the character span this token occupied in the original input line.
+	pub fn position(&self) -> Position
+	{
+		match *self
+		{
+			TokenType::Delim(_, pos)  => pos,
+			TokenType::Normal(_, pos) => pos,
+		}
+	}
+}
+
+/* Attemtps to tokenizes a line according to the syntax described by 'checker'.
+ * If the line's syntax is valid, a vector of TokenType wrapped strings will be
+ * returned. Otherwise, a SyntaxError will be raised or propogated.
+ *
+ * Parameters:
+ *   - line    : string of text to be tokenized
+ *   - checker : set of syntax rules to tokenize with. must implement
+ *               SyntaxChecker trait
+ *
+ * Important Notes:
+ *   - delimiters implicitly separate two tokens even if one of those tokens is
+ *     is empty. Thus this routine WILL generate blank tokens to either side of
+ *     delimiters as necessary such as when they are chained or when they begin
+ *     or end a line. It is the CALLER's reponsibility to deal with blank tokens
+ *   - this routine discards comments ENTIRELY. neither the comment delimiter
+ *     nor the comment will be present in the result vector. this is because
+ *     by definition comments are semantically meaningless.
+ */
+pub fn tokenize<S: SyntaxChecker>(line: &str, checker: &mut S) -> Result<Vec<TokenType>, SyntaxError>
+{
+	if line.is_empty()
+	{
+		let mut tokens = Vec::with_capacity(1);
+		tokens.push(TokenType::Normal(String::new(), Position::new(0, 0)));
+		return Ok(tokens);
+	}
+	let mut buffer = String::with_capacity(line.len()); // biggest token possible is the line unmodified
+	let mut tokens = Vec::with_capacity(5);
+	let mut delim_pushed = false;
+	let mut last: usize = 0;
+	let mut last_ch: char = '\0';
+	let mut token_start: usize = 0; // index where the token currently in 'buffer' began
+
+	for (index, ch) in line.chars().enumerate()
+	{
+		if checker.is_esc(ch) && !checker.esc_set()
+		{
+			checker.set_esc(true);
+		}
+		else if checker.esc_set()
+		{
+			if checker.is_delim(ch) || checker.is_esc(ch) || checker.is_comment(ch)
+			{
+				buffer.push(ch);
+				checker.set_esc(false);
+				delim_pushed = false;
+			}
+			else if checker.is_preserved_delim(ch)
+			{
+				buffer.push(checker.esc_char());
+				buffer.push(ch);
+				checker.set_esc(false);
+				delim_pushed = false;
+			}
+			else
+			{
+				last = index;
+				last_ch = ch;
+				break;
+			}
+		}
+		else if checker.is_delim(ch)
+		{
+			let mut new_token = buffer.clone();
+			new_token.shrink_to_fit();
+			checker.feed_token(&new_token, !DELIM, token_start..index);
+
+			tokens.push(TokenType::Normal(new_token, Position::from(token_start..index)));
+
+			buffer.clear();
+			buffer.push(ch);
+
+			new_token = buffer.clone();
+			new_token.shrink_to_fit();
+			checker.feed_token(&new_token, DELIM, index..(index+1));
+
+			tokens.push(TokenType::Delim(new_token, Position::from(index..(index+1))));
+
+			buffer.clear();
+			token_start = index + 1;
+			delim_pushed = true;
+		}
+		else if checker.is_comment(ch)
+		{
+			let mut new_token = buffer.clone();
+			new_token.shrink_to_fit();
+
+			checker.feed_token(&new_token, !DELIM, token_start..index);
+
+			tokens.push(TokenType::Normal(new_token, Position::from(token_start..index)));
+			try!(checker.assert_valid(index..(index+1), true));
+			return Ok(tokens); // if we reach a comment, immediately exit
+		}
+		else
+		{
+			buffer.push(ch);
+			delim_pushed = false;
+		}
+
+		try!(checker.assert_valid(index..(index+1), true));
+		last = index;
+		last_ch = ch;
+	}
+
+	if checker.esc_set()
+	{
+		return Err(SyntaxError::BadEscSeq(last..(last+1),
+						if last_ch == checker.esc_char()
+						{
+							'\0'
+						}
+						else
+						{
+							last_ch
+						})
+		);
+	}
+
+	let mut new_token = String::new();
+
+	if !buffer.is_empty()
+	{
+		new_token = buffer.clone();
+		new_token.shrink_to_fit();
+		checker.feed_token(&new_token, !DELIM, token_start..(last+1));
+		tokens.push(TokenType::Normal(new_token, Position::from(token_start..(last+1))));
+	}
+	else if delim_pushed
+	{
+		checker.feed_token(&new_token, !DELIM, token_start..token_start);
+		tokens.push(TokenType::Normal(new_token, Position::from(token_start..token_start)));
+	}
+
+	try!(checker.assert_valid(last..(last+1), false));
+
+	Ok(tokens)
+}
+
+// metric prefix meaning "no prefix"; distinct from any recognized prefix
+// character so it can be compared against directly.
+pub const NO_PREFIX: char = '\0';
+
+// Resolves a metric prefix character to the multiplier it represents, or
+// 'None' if 'prefix' isn't one Yucon recognizes.
+pub fn prefix_as_num(prefix: char) -> Option<f64>
+{
+	let num: f64 = match prefix
+	{
+	'Y' => 1.0e24,
+	'Z' => 1.0e21,
+	'E' => 1.0e18,
+	'P' => 1.0e15,
+	'T' => 1.0e12,
+	'G' => 1.0e9,
+	'M' => 1.0e6,
+	'k' => 1.0e3,
+	'h' => 1.0e2,
+	'D' => 1.0e1,
+	NO_PREFIX => 1.0,
+	'd' => 1.0e-1,
+	'c' => 1.0e-2,
+	'm' => 1.0e-3,
+	'u' => 1.0e-6,
+	'n' => 1.0e-9,
+	'p' => 1.0e-12,
+	'f' => 1.0e-15,
+	'a' => 1.0e-18,
+	'z' => 1.0e-21,
+	'y' => 1.0e-24,
+	_   => return None,
+	};
+
+	Some(num)
+}